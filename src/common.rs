@@ -1,17 +1,215 @@
-#[derive(Debug, Eq, PartialEq)]
+use std::{error, fmt, io, num};
+
+use super::membership::HedgedTerm;
+
+#[derive(Debug)]
 pub enum FuzzyError {
-    InvalidPoints,
+    /// Malformed points of a curve, with the reason.
+    InvalidPoints(String),
+    /// Point with NaN or infinite coordinate.
+    NonFinitePoint { x: f64, y: f64 },
+    /// Membership outside of [0, 1].
+    MembershipOutOfRange { x: f64, y: f64 },
+    /// Two points at the same x with different memberships.
+    ConflictingPoints { x: f64, first: f64, second: f64 },
+    InvalidParameters(String),
+    /// Value outside of the universe of a bounded set.
+    OutOfRange { x: f64, min: f64, max: f64 },
     InvalidCategory(String),
     InvalidTerm(String),
+    EmptySet,
+    NoRuleFired(String),
+    NonMonotonicTerm(String),
+    /// Problems found by `Fuzzer::validate`, paired with indices of offending rules.
+    InvalidRules(Vec<(usize, FuzzyError)>),
+    /// Malformed rule text, position is counted from 1.
+    Parse { line: usize, column: usize, message: String },
+    /// Error raised by a term of a set.
+    InTerm { term: Term, source: Box<FuzzyError> },
+    /// Error raised by an input or output category.
+    InCategory { category: Category, source: Box<FuzzyError> },
+    /// Error raised by rule with given index.
+    InRule { index: usize, source: Box<FuzzyError> },
+    Io(io::Error),
+    ParseFloat(num::ParseFloatError),
+    ParseInt(num::ParseIntError),
     Misc(String)
 }
 
+impl FuzzyError {
+    /// Wraps error with the term it occurred in.
+    pub fn in_term(
+        self,
+        term: impl Into<Term>
+    ) -> Self {
+        FuzzyError::InTerm { term: term.into(), source: Box::new(self) }
+    }
+
+    /// Wraps error with the category it occurred in.
+    pub fn in_category(
+        self,
+        category: impl Into<Category>
+    ) -> Self {
+        FuzzyError::InCategory { category: category.into(), source: Box::new(self) }
+    }
+
+    /// Wraps error with index of the rule it occurred in.
+    pub fn in_rule(
+        self,
+        index: usize
+    ) -> Self {
+        FuzzyError::InRule { index, source: Box::new(self) }
+    }
+
+    /// Innermost error, with all context stripped.
+    pub fn root(
+        &self
+    ) -> &FuzzyError {
+        match self {
+            FuzzyError::InTerm { source, .. }
+            | FuzzyError::InCategory { source, .. }
+            | FuzzyError::InRule { source, .. } => source.root(),
+            other => other
+        }
+    }
+}
+
+impl fmt::Display for FuzzyError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        match self {
+            FuzzyError::InvalidPoints(reason) => write!(f, "invalid points: {}", reason),
+            FuzzyError::NonFinitePoint { x, y } => write!(f, "non-finite point ({}, {})", x, y),
+            FuzzyError::MembershipOutOfRange { x, y } =>
+                write!(f, "membership {} at x = {} is outside of [0, 1]", y, x),
+            FuzzyError::ConflictingPoints { x, first, second } =>
+                write!(f, "conflicting memberships {} and {} at x = {}", first, second, x),
+            FuzzyError::OutOfRange { x, min, max } =>
+                write!(f, "value {} is outside of universe [{}, {}]", x, min, max),
+            FuzzyError::InvalidParameters(reason) => write!(f, "invalid parameters: {}", reason),
+            FuzzyError::InvalidCategory(category) => write!(f, "unknown category '{}'", category),
+            FuzzyError::InvalidTerm(term) => write!(f, "unknown term '{}'", term),
+            FuzzyError::EmptySet => write!(f, "set is empty"),
+            FuzzyError::NoRuleFired(category) => write!(f, "no rule fired for output '{}'", category),
+            FuzzyError::NonMonotonicTerm(term) => write!(f, "term '{}' is not monotonic", term),
+            FuzzyError::InvalidRules(issues) => {
+                write!(f, "invalid rules:")?;
+                for (index, error) in issues {
+                    write!(f, " rule {}: {};", index, error)?;
+                }
+                Ok(())
+            },
+            FuzzyError::Parse { line, column, message } =>
+                write!(f, "line {}, column {}: {}", line, column, message),
+            FuzzyError::InTerm { term, source } => write!(f, "in term '{}': {}", term, source),
+            FuzzyError::InCategory { category, source } =>
+                write!(f, "in category '{}': {}", category, source),
+            FuzzyError::InRule { index, source } => write!(f, "in rule {}: {}", index, source),
+            FuzzyError::Io(error) => write!(f, "{}", error),
+            FuzzyError::ParseFloat(error) => write!(f, "{}", error),
+            FuzzyError::ParseInt(error) => write!(f, "{}", error),
+            FuzzyError::Misc(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl error::Error for FuzzyError {
+    fn source(
+        &self
+    ) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FuzzyError::InTerm { source, .. }
+            | FuzzyError::InCategory { source, .. }
+            | FuzzyError::InRule { source, .. } => Some(source.as_ref()),
+            FuzzyError::Io(error) => Some(error),
+            FuzzyError::ParseFloat(error) => Some(error),
+            FuzzyError::ParseInt(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+/// I/O errors aren't comparable, so their kinds and messages are compared instead.
+/// NaN coordinates are equal to each other, so that errors about them can be matched.
+impl PartialEq for FuzzyError {
+    fn eq(
+        &self,
+        other: &Self
+    ) -> bool {
+        use FuzzyError::*;
+        let same = |a: f64, b: f64| a == b || (a.is_nan() && b.is_nan());
+        match (self, other) {
+            (InvalidPoints(a), InvalidPoints(b))
+            | (InvalidParameters(a), InvalidParameters(b))
+            | (InvalidCategory(a), InvalidCategory(b))
+            | (InvalidTerm(a), InvalidTerm(b))
+            | (NoRuleFired(a), NoRuleFired(b))
+            | (NonMonotonicTerm(a), NonMonotonicTerm(b))
+            | (Misc(a), Misc(b)) => a == b,
+            (EmptySet, EmptySet) => true,
+            (NonFinitePoint { x: x1, y: y1 }, NonFinitePoint { x: x2, y: y2 })
+            | (MembershipOutOfRange { x: x1, y: y1 }, MembershipOutOfRange { x: x2, y: y2 }) =>
+                same(*x1, *x2) && same(*y1, *y2),
+            (OutOfRange { x: x1, min: a1, max: b1 }, OutOfRange { x: x2, min: a2, max: b2 }) =>
+                same(*x1, *x2) && same(*a1, *a2) && same(*b1, *b2),
+            (ConflictingPoints { x: x1, first: f1, second: s1 }, ConflictingPoints { x: x2, first: f2, second: s2 }) =>
+                same(*x1, *x2) && same(*f1, *f2) && same(*s1, *s2),
+            (InvalidRules(a), InvalidRules(b)) => a == b,
+            (Parse { line: l1, column: c1, message: m1 }, Parse { line: l2, column: c2, message: m2 }) =>
+                (l1, c1, m1) == (l2, c2, m2),
+            (InTerm { term: a, source: s1 }, InTerm { term: b, source: s2 })
+            | (InCategory { category: a, source: s1 }, InCategory { category: b, source: s2 }) =>
+                a == b && s1 == s2,
+            (InRule { index: a, source: s1 }, InRule { index: b, source: s2 }) => a == b && s1 == s2,
+            (Io(a), Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            (ParseFloat(a), ParseFloat(b)) => a == b,
+            (ParseInt(a), ParseInt(b)) => a == b,
+            _ => false
+        }
+    }
+}
+
+impl From<io::Error> for FuzzyError {
+    fn from(
+        error: io::Error
+    ) -> Self {
+        FuzzyError::Io(error)
+    }
+}
+
+impl From<num::ParseFloatError> for FuzzyError {
+    fn from(
+        error: num::ParseFloatError
+    ) -> Self {
+        FuzzyError::ParseFloat(error)
+    }
+}
+
+impl From<num::ParseIntError> for FuzzyError {
+    fn from(
+        error: num::ParseIntError
+    ) -> Self {
+        FuzzyError::ParseInt(error)
+    }
+}
+
 pub type FuzzyResult<T> = Result<T, FuzzyError>;
 
 pub type Category = String;
 pub type Term = String;
-pub type FuzzyValue = (Category, Term, f64);
-pub type FuzzyIdent = (Category, Term);
+pub type FuzzyValue = (Category, HedgedTerm, f64);
+pub type FuzzyIdent = (Category, HedgedTerm);
+
+/// Serializes map with keys in sorted order, so that output is deterministic.
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_sorted<V: serde::Serialize, S: serde::Serializer>(
+    map: &std::collections::HashMap<String, V>,
+    serializer: S
+) -> Result<S::Ok, S::Error> {
+    serde::Serialize::serialize(&map.iter().collect::<std::collections::BTreeMap<_, _>>(), serializer)
+}
 
 #[macro_export]
 macro_rules! values {
@@ -21,3 +219,26 @@ macro_rules! values {
         map
     }}
 }
+
+#[test]
+fn test_errors(
+) {
+    use std::error::Error;
+    let error = FuzzyError::InvalidPoints("polyline needs at least 2 points, got 1".to_string())
+        .in_term("quiet")
+        .in_category("loudness");
+    assert_eq!(error.to_string(),
+               "in category 'loudness': in term 'quiet': invalid points: polyline needs at least 2 points, got 1");
+    assert_eq!(error.root(), &FuzzyError::InvalidPoints("polyline needs at least 2 points, got 1".to_string()));
+    assert_eq!(error.source().unwrap().to_string(),
+               "in term 'quiet': invalid points: polyline needs at least 2 points, got 1");
+
+    let parse = || -> FuzzyResult<f64> { Ok("x".parse::<f64>()?) };
+    assert!(matches!(parse(), Err(FuzzyError::ParseFloat(_))));
+    let read = || -> FuzzyResult<String> { Ok(std::fs::read_to_string("/nonexistent")?) };
+    let error = read().unwrap_err();
+    assert!(matches!(error, FuzzyError::Io(_)));
+    assert!(error.source().is_some());
+    assert_eq!(FuzzyError::Parse { line: 2, column: 5, message: "expected IS".to_string() }.to_string(),
+               "line 2, column 5: expected IS");
+}
@@ -1,44 +1,335 @@
 use super::common::{FuzzyError, FuzzyResult};
 
+/// Method of reducing output set to a single crisp value.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Defuzzifier {
+    /// Center of gravity. See `cog`.
+    #[default]
+    Centroid,
+    /// See `bisector`.
+    Bisector,
+    /// See `mom`.
+    MeanOfMaximum,
+    /// See `som`.
+    SmallestOfMaximum,
+    /// See `lom`.
+    LargestOfMaximum,
+    /// See `first_of_maxima`.
+    FirstOfMaxima,
+    /// See `last_of_maxima`.
+    LastOfMaxima,
+    /// See `center_of_sums`. Works on consequents of individual rules.
+    CenterOfSums,
+    /// See `height`. Works on consequents of individual rules.
+    Height
+}
+
+impl Defuzzifier {
+    /// Calculates crisp value of aggregated output set.
+    /// Consequents of individual rules are used by CenterOfSums and Height methods only.
+    pub fn defuzzify<P: AsRef<[(f64, f64)]>>(
+        &self,
+        aggregated: impl AsRef<[(f64, f64)]>,
+        consequents: &[P]
+    ) -> FuzzyResult<f64> {
+        match self {
+            Defuzzifier::Centroid => cog(aggregated),
+            Defuzzifier::Bisector => bisector(aggregated),
+            Defuzzifier::MeanOfMaximum => mom(aggregated),
+            Defuzzifier::SmallestOfMaximum => som(aggregated),
+            Defuzzifier::LargestOfMaximum => lom(aggregated),
+            Defuzzifier::FirstOfMaxima => first_of_maxima(aggregated),
+            Defuzzifier::LastOfMaxima => last_of_maxima(aggregated),
+            Defuzzifier::CenterOfSums => center_of_sums(consequents),
+            Defuzzifier::Height => height(consequents)
+        }
+    }
+
+    /// Calculates crisp value of output set with periodic universe [0, period).
+    /// Centroid is calculated on a circle, see `circular_cog`.
+    /// Other methods work on the curve unrolled over [0, period].
+    pub fn defuzzify_periodic<P: AsRef<[(f64, f64)]>>(
+        &self,
+        aggregated: impl AsRef<[(f64, f64)]>,
+        consequents: &[P],
+        period: f64
+    ) -> FuzzyResult<f64> {
+        match self {
+            Defuzzifier::Centroid => circular_cog(aggregated, period),
+            other => other.defuzzify(aggregated, consequents).map(|x| x.rem_euclid(period))
+        }
+    }
+}
+
+/// Center of gravity, exact for polylines: every segment contributes the area of its
+/// trapezoid at the trapezoid's centroid.
+/// ```text
 ///              / - - -
 ///             /
 ///      - - - /
 ///    /
 ///   /
 ///- -
+/// ```
 pub fn cog(
     points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    let (moment, area) = moments(points.as_ref())?;
+    if area == 0.0 {
+        Err(FuzzyError::EmptySet)?
+    }
+    Ok(moment/area)
+}
+
+/// Center of gravity on a circle of given circumference, e.g. 360 for headings.
+/// Every x is treated as a direction, so that mass on both sides of 0 averages
+/// to 0 rather than to period/2. Result is in [0, period).
+pub fn circular_cog(
+    points: impl AsRef<[(f64, f64)]>,
+    period: f64
 ) -> FuzzyResult<f64> {
     let points = points.as_ref();
-    if points.len() < 2 {
-        Err(FuzzyError::InvalidPoints)?
+    let (_, area) = moments(points)?;
+    if area == 0.0 {
+        Err(FuzzyError::EmptySet)?
     }
-    let (mut a, mut b) = (0.0, 0.0);
+    let w = 2.0*std::f64::consts::PI/period;
+    // Integrals of membership times cos and sin of the angle, exact for linear segments.
+    let (mut c, mut s) = (0.0, 0.0);
+    for pair in points.windows(2) {
+        let (x1, y1) = pair[0];
+        let (x2, y2) = pair[1];
+        if x1 == x2 {
+            continue;
+        }
+        let slope = (y2-y1)/(x2-x1);
+        let cos = |x: f64, y: f64| y*(w*x).sin()/w+slope*(w*x).cos()/(w*w);
+        let sin = |x: f64, y: f64| -y*(w*x).cos()/w+slope*(w*x).sin()/(w*w);
+        c += cos(x2, y2)-cos(x1, y1);
+        s += sin(x2, y2)-sin(x1, y1);
+    }
+    // Mass spread evenly around the circle has no direction.
+    if c.hypot(s) <= 1e-9*area {
+        Err(FuzzyError::InvalidPoints("circular centroid of evenly spread set is undefined".to_string()))?
+    }
+    Ok((s.atan2(c)/w).rem_euclid(period))
+}
+
+/// Point dividing area under the curve into two equal parts.
+pub fn bisector(
+    points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    let points = points.as_ref();
+    let (_, area) = moments(points)?;
+    if area == 0.0 {
+        Err(FuzzyError::EmptySet)?
+    }
+    let mut left = area/2.0;
     for pair in points.windows(2) {
         let (x1, y1) = pair[0];
         let (x2, y2) = pair[1];
-        let (x_diff, y_diff) = (x2-x1, (y2-y1).abs());
-        // Height of base rectangle.
-        let height = f64::min(y1, y2);
-        // Area of base rectangle + right traingle on the top.
-        let area = height*x_diff + 0.5*y_diff*x_diff;
-        let centroid = if y_diff == 0.0 {
-            // | - - - - |
-            // |         |
-            // |         |
-            // |         |
-            // | - - - - |
-            (x1+x2)/2.0
+        let segment = (y1+y2)/2.0*(x2-x1);
+        if segment < left {
+            left -= segment;
+            continue;
+        }
+        // Area between x1 and x1+t equals y1*t + slope*t^2/2.
+        let slope = (y2-y1)/(x2-x1);
+        let t = if slope == 0.0 {
+            left/y1
         } else {
-            // |      -  |
-            // |   / |   |
-            // | -   |   |
-            // |  |  |   |
-            // | - - - - |
-            (2.0*x1+x2)/3.0
+            (-y1+(y1*y1+2.0*slope*left).sqrt())/slope
         };
-        a += area*centroid;
+        return Ok(x1+t)
+    }
+    Ok(points.last().unwrap().0)
+}
+
+/// Mean of maximum. Plateaus are weighted by their length, isolated peaks are averaged.
+pub fn mom(
+    points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    let maxima = maxima(points.as_ref())?;
+    let length = maxima.iter().map(|(a, b)| b-a).sum::<f64>();
+    if length > 0.0 {
+        Ok(maxima.iter().map(|(a, b)| (b-a)*(a+b)/2.0).sum::<f64>()/length)
+    } else {
+        Ok(maxima.iter().map(|(a, _)| a).sum::<f64>()/maxima.len() as f64)
+    }
+}
+
+/// Smallest (in absolute value) of maximum.
+pub fn som(
+    points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    let maxima = maxima(points.as_ref())?;
+    Ok(maxima.iter()
+       .flat_map(|(a, b)| vec![*a, *b])
+       .fold(f64::INFINITY, |acc, x| if x.abs() < acc.abs() { x } else { acc }))
+}
+
+/// Largest (in absolute value) of maximum.
+pub fn lom(
+    points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    let maxima = maxima(points.as_ref())?;
+    Ok(maxima.iter()
+       .flat_map(|(a, b)| vec![*a, *b])
+       .fold(0.0, |acc: f64, x| if x.abs() > acc.abs() { x } else { acc }))
+}
+
+/// Leftmost point where maximum is reached.
+pub fn first_of_maxima(
+    points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    Ok(maxima(points.as_ref())?.first().unwrap().0)
+}
+
+/// Rightmost point where maximum is reached.
+pub fn last_of_maxima(
+    points: impl AsRef<[(f64, f64)]>
+) -> FuzzyResult<f64> {
+    Ok(maxima(points.as_ref())?.last().unwrap().1)
+}
+
+/// Center of sums. Like COG of summed sets, but overlapping areas are counted multiple times.
+pub fn center_of_sums<P: AsRef<[(f64, f64)]>>(
+    sets: &[P]
+) -> FuzzyResult<f64> {
+    let (mut a, mut b) = (0.0, 0.0);
+    for set in sets {
+        let (moment, area) = moments(set.as_ref())?;
+        a += moment;
         b += area;
     }
+    if b == 0.0 {
+        Err(FuzzyError::EmptySet)?
+    }
     Ok(a/b)
 }
+
+/// Height (weighted average) method. Mean of maximum of each set weighted by its height.
+pub fn height<P: AsRef<[(f64, f64)]>>(
+    sets: &[P]
+) -> FuzzyResult<f64> {
+    let (mut a, mut b) = (0.0, 0.0);
+    for set in sets {
+        let set = set.as_ref();
+        let h = set.iter().fold(0.0, |acc: f64, (_, y)| acc.max(*y));
+        if h > 0.0 {
+            a += h*mom(set)?;
+            b += h;
+        }
+    }
+    if b == 0.0 {
+        Err(FuzzyError::EmptySet)?
+    }
+    Ok(a/b)
+}
+
+/// First moment and area under the curve.
+fn moments(
+    points: &[(f64, f64)]
+) -> FuzzyResult<(f64, f64)> {
+    if points.len() < 2 {
+        Err(FuzzyError::InvalidPoints(format!("curve needs at least 2 points, got {}", points.len())))?
+    }
+    let (mut a, mut b) = (0.0, 0.0);
+    for pair in points.windows(2) {
+        let (x1, y1) = pair[0];
+        let (x2, y2) = pair[1];
+        let x_diff = x2-x1;
+        // Trapezoid under the segment.
+        let area = (y1+y2)/2.0*x_diff;
+        if area == 0.0 {
+            continue;
+        }
+        // |      -  |
+        // |   / |   |
+        // | -   |   |
+        // |  |  |   |
+        // | - - - - |
+        let centroid = x1+x_diff*(y1+2.0*y2)/(3.0*(y1+y2));
+        a += area*centroid;
+        b += area;
+    }
+    Ok((a, b))
+}
+
+/// Intervals (possibly degenerated to a point) where maximum is reached.
+fn maxima(
+    points: &[(f64, f64)]
+) -> FuzzyResult<Vec<(f64, f64)>> {
+    if points.len() < 2 {
+        Err(FuzzyError::InvalidPoints(format!("curve needs at least 2 points, got {}", points.len())))?
+    }
+    let max = points.iter().fold(0.0, |acc: f64, (_, y)| acc.max(*y));
+    if max == 0.0 {
+        Err(FuzzyError::EmptySet)?
+    }
+    let mut intervals: Vec<(f64, f64)> = Vec::new();
+    let mut previous = false;
+    for (x, y) in points.iter() {
+        let current = *y == max;
+        match (previous, current, intervals.last_mut()) {
+            (true, true, Some(interval)) => interval.1 = *x,
+            (_, true, _) => intervals.push((*x, *x)),
+            _ => {}
+        }
+        previous = current;
+    }
+    Ok(intervals)
+}
+
+#[test]
+fn test_defuzz(
+) -> FuzzyResult<()> {
+    //     - - -
+    //   /       \
+    // -           - - - - -
+    // 0 1 2 3 4 5 6 7 8 9 10
+    let set = vec![(0.0, 0.0), (1.0, 0.0), (2.0, 1.0), (4.0, 1.0), (5.0, 0.0), (10.0, 0.0)];
+    assert_eq!(cog(&set)?, 3.0);
+    assert_eq!(bisector(&set)?, 3.0);
+    assert_eq!(mom(&set)?, 3.0);
+    assert_eq!(som(&set)?, 2.0);
+    assert_eq!(lom(&set)?, 4.0);
+    assert_eq!(first_of_maxima(&set)?, 2.0);
+    assert_eq!(last_of_maxima(&set)?, 4.0);
+
+    // Right triangle, 2/3 of the way to the peak.
+    let triangle = vec![(0.0, 0.0), (3.0, 1.0)];
+    assert_eq!(cog(&triangle)?, 2.0);
+    // Rectangle 1 at x=1, triangle 0.5 at x=4/3 and rectangle 2 at x=3: (23/3) / 3.5.
+    let trapezoid = vec![(0.0, 0.5), (2.0, 1.0), (4.0, 1.0)];
+    assert!((cog(&trapezoid)?-46.0/21.0).abs() < 1e-12);
+    assert!((bisector(&triangle)?-3.0/2f64.sqrt()).abs() < 1e-12);
+    assert_eq!(mom(&triangle)?, 3.0);
+
+    let negative = vec![(-4.0, 1.0), (-3.0, 0.0), (1.0, 0.0), (2.0, 1.0)];
+    assert_eq!(som(&negative)?, 2.0);
+    assert_eq!(lom(&negative)?, -4.0);
+    assert_eq!(first_of_maxima(&negative)?, -4.0);
+    assert_eq!(last_of_maxima(&negative)?, 2.0);
+    assert_eq!(mom(&negative)?, -1.0);
+
+    let sets = vec![
+        vec![(0.0, 0.0), (1.0, 0.5), (3.0, 0.5), (4.0, 0.0)],
+        vec![(2.0, 0.0), (3.0, 1.0), (4.0, 0.0)]
+    ];
+    assert_eq!(center_of_sums(&sets)?, (2.0*1.5+3.0*1.0)/2.5);
+    assert_eq!(height(&sets)?, (0.5*2.0+1.0*3.0)/1.5);
+    assert_eq!(Defuzzifier::Height.defuzzify(&set, &sets)?, height(&sets)?);
+
+    assert_eq!(cog(vec![(0.0, 0.0), (1.0, 0.0)]), Err(FuzzyError::EmptySet));
+    assert_eq!(mom(vec![(0.0, 0.0)]), Err(FuzzyError::InvalidPoints("curve needs at least 2 points, got 1".to_string())));
+
+    // Mass on both sides of 0 averages to 0, not to 180.
+    let north = circular_cog(vec![(0.0, 1.0), (10.0, 0.0), (350.0, 0.0), (360.0, 1.0)], 360.0)?;
+    assert!(north.min(360.0-north) < 1e-9);
+    assert!((circular_cog(vec![(80.0, 0.0), (90.0, 1.0), (100.0, 0.0)], 360.0)?-90.0).abs() < 1e-9);
+    assert!(circular_cog(vec![(0.0, 1.0), (360.0, 1.0)], 360.0).is_err());
+    Ok(())
+}
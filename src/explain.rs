@@ -0,0 +1,195 @@
+use std::fmt;
+
+use super::defuzz::Defuzzifier;
+use super::common::{Category, Term};
+
+/// Intermediate results of a single inference, returned by `Fuzzer::explain`.
+/// Rendered as text with `Display`, and as JSON with the `serde` feature.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Explanation {
+    /// Inputs sorted by category.
+    pub inputs: Vec<InputExplanation>,
+    /// Rules in order of definition.
+    pub rules: Vec<RuleExplanation>,
+    /// Outputs sorted by category.
+    pub outputs: Vec<OutputExplanation>
+}
+
+/// Membership of an input value in every term of its category.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InputExplanation {
+    pub category: Category,
+    pub value: f64,
+    /// Terms sorted by name.
+    pub memberships: Vec<(Term, f64)>
+}
+
+/// Firing strength of a rule, weight included.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RuleExplanation {
+    pub index: usize,
+    /// Rule in the syntax of `parse_rules`.
+    pub rule: String,
+    pub strength: f64
+}
+
+/// How crisp value of an output is calculated.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Method {
+    /// Aggregated output set reduced with defuzzifier.
+    Mamdani(Defuzzifier),
+    /// Weighted average of rule outputs.
+    Sugeno,
+    /// Weighted average of firing strengths inverted through consequent terms.
+    Tsukamoto
+}
+
+/// Contribution of a single rule to an output.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConsequentExplanation {
+    pub term: String,
+    pub strength: f64,
+    /// Consequent set clipped or scaled by implication. Empty for Sugeno and Tsukamoto outputs.
+    pub points: Vec<(f64, f64)>,
+    /// Crisp value of the rule. Only for Sugeno and Tsukamoto outputs.
+    pub value: Option<f64>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OutputExplanation {
+    pub category: Category,
+    pub method: Method,
+    pub consequents: Vec<ConsequentExplanation>,
+    /// Aggregated output set. Empty for Sugeno and Tsukamoto outputs.
+    pub aggregated: Vec<(f64, f64)>,
+    /// Crisp value, `None` if no rule fired.
+    pub value: Option<f64>
+}
+
+impl fmt::Display for Method {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        match self {
+            Method::Mamdani(defuzzifier) => write!(f, "mamdani, {:?}", defuzzifier),
+            Method::Sugeno => write!(f, "sugeno"),
+            Method::Tsukamoto => write!(f, "tsukamoto")
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        writeln!(f, "Inputs:")?;
+        for input in self.inputs.iter() {
+            writeln!(f, "  {} = {}", input.category, input.value)?;
+            for (term, y) in input.memberships.iter() {
+                writeln!(f, "    {}: {:.4}", term, y)?;
+            }
+        }
+        writeln!(f, "Rules:")?;
+        for rule in self.rules.iter() {
+            writeln!(f, "  {}. {} => {:.4}", rule.index, rule.rule, rule.strength)?;
+        }
+        writeln!(f, "Outputs:")?;
+        for output in self.outputs.iter() {
+            match output.value {
+                Some(y) => writeln!(f, "  {} ({}) = {:.4}", output.category, output.method, y)?,
+                None => writeln!(f, "  {} ({}): no rule fired", output.category, output.method)?
+            }
+            for consequent in output.consequents.iter() {
+                write!(f, "    {}: {:.4}", consequent.term, consequent.strength)?;
+                match consequent.value {
+                    Some(y) => writeln!(f, " -> {:.4}", y)?,
+                    None => writeln!(f, " -> {} points", consequent.points.len())?
+                }
+            }
+            if !output.aggregated.is_empty() {
+                let height = output.aggregated.iter().fold(0.0, |acc: f64, (_, y)| acc.max(*y));
+                writeln!(f, "    aggregated: {} points, height {:.4}", output.aggregated.len(), height)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_explain(
+) -> crate::FuzzyResult<()> {
+    use crate::{Fuzzer, sugeno::SugenoSet};
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0); "low" => (0.0, 1.0), (10.0, 0.0) }?
+    ).defuzzify(
+        "c",
+        crate::fuzzy! { "high" => (0.0, 0.0), (4.0, 1.0), (8.0, 0.0) }?
+    ).sugeno(
+        "s",
+        SugenoSet::new().constant("one", 1.0).constant("zero", 0.0)
+    ).rules_from_str("
+        IF a IS high THEN c IS high
+        IF a IS high THEN s IS one
+        IF a IS low THEN s IS zero WITH 0.5
+    ")?;
+    let values = crate::values! { "a" => 5.0 };
+    let explanation = fuzzer.explain(&values)?;
+
+    assert_eq!(explanation.inputs, vec![InputExplanation {
+        category: "a".to_string(),
+        value: 5.0,
+        memberships: vec![("high".to_string(), 0.5), ("low".to_string(), 0.5)]
+    }]);
+    assert_eq!(explanation.rules.iter().map(|rule| rule.strength).collect::<Vec<_>>(), vec![0.5, 0.5, 0.25]);
+    assert_eq!(explanation.rules[2].rule, "IF a IS low THEN s IS zero WITH 0.5");
+
+    let c = &explanation.outputs[0];
+    assert_eq!(c.method, Method::Mamdani(Defuzzifier::Centroid));
+    assert_eq!(c.consequents[0].points, vec![(0.0, 0.0), (2.0, 0.5), (6.0, 0.5), (8.0, 0.0)]);
+    assert_eq!(c.value, Some(4.0));
+    let s = &explanation.outputs[1];
+    assert_eq!(s.consequents.iter().map(|c| c.value).collect::<Vec<_>>(), vec![Some(1.0), Some(0.0)]);
+    assert_eq!(s.value, Some(0.5/0.75));
+    let crisp = fuzzer.evaluate(&values)?;
+    assert_eq!((crisp["c"], crisp["s"]), (4.0, 0.5/0.75));
+
+    let text = explanation.to_string();
+    assert!(text.contains("    high: 0.5000\n"));
+    assert!(text.contains("  2. IF a IS low THEN s IS zero WITH 0.5 => 0.2500\n"));
+    assert!(text.contains("  c (mamdani, Centroid) = 4.0000\n"));
+    assert!(text.contains("  s (sugeno) = 0.6667\n"));
+
+    let none = fuzzer.explain(&crate::values! { "a" => 0.0 })?;
+    assert_eq!(none.outputs[0].value, None);
+    assert!(none.to_string().contains("  c (mamdani, Centroid): no rule fired\n"));
+
+    // Clipped shoulders keep their plateau in the reported points.
+    let shoulder = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).defuzzify(
+        "c",
+        crate::fuzzy! { "low" => (0.0, 1.0), (2.0, 1.0), (4.0, 0.0) }?
+    ).rules_from_str("IF a IS high THEN c IS low")?.explain(&values)?;
+    assert_eq!(shoulder.outputs[0].consequents[0].points, vec![(0.0, 0.5), (3.0, 0.5), (4.0, 0.0)]);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_value(&explanation).unwrap();
+        assert_eq!(json["outputs"][0]["method"], serde_json::json!({ "mamdani": "centroid" }));
+        assert_eq!(json["rules"][0]["strength"], 0.5);
+    }
+    Ok(())
+}
@@ -0,0 +1,723 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use super::fuzz::{Fuzzer, FuzzerConfig, FuzzyRule};
+use super::set::{FuzzySet, RangePolicy};
+use super::sugeno::{SugenoSet, SugenoTerm};
+use super::membership::MembershipFunction;
+use super::ops::{TNorm, SNorm, Implication, Aggregation};
+use super::defuzz::Defuzzifier;
+use super::parse::quote;
+use super::common::{Category, FuzzyError, FuzzyResult};
+
+/// Reads Fuzzy Control Language (IEC 61131-7) function block.
+///
+/// Terms are given as points `(x, y) ...` or, following jFuzzyLogic, as
+/// `trian a b c`, `trape a b c d`, `gauss mean sigma`, `gbell a b mean`, `sigm gain center`.
+/// Outputs consisting of singletons only become Sugeno outputs, COGS is accepted for them only.
+/// RANGE becomes universe of the set, DEFAULT is accepted but ignored.
+/// All rule blocks have to share operators.
+/// The result is checked with `Fuzzer::validate`, so rules can't reference unknown terms.
+pub fn from_fcl(
+    source: &str
+) -> FuzzyResult<Fuzzer> {
+    let source = strip_comments(source);
+    let mut parser = Parser { source: &source, tokens: tokenize(&source)?, position: 0 };
+    let fuzzer = parser.function_block()?;
+    fuzzer.validate()?;
+    Ok(fuzzer)
+}
+
+/// Reads FCL function block from file, see `from_fcl`.
+pub fn read_fcl(
+    path: impl AsRef<std::path::Path>
+) -> FuzzyResult<Fuzzer> {
+    from_fcl(&std::fs::read_to_string(path)?)
+}
+
+/// Writes Fuzzer as FCL function block. Categories and terms are sorted by name.
+///
+/// S, Z, Pi and hedged terms are written as sampled points. Names, which are not plain
+/// identifiers, are quoted, which is understood by `from_fcl` but is not standard FCL.
+/// Fails for operators, methods, periodic sets, out of range policies other than
+/// `RangePolicy::Clamp` and outputs without FCL equivalent.
+pub fn to_fcl(
+    fuzzer: &Fuzzer,
+    name: &str
+) -> FuzzyResult<String> {
+    let config = &fuzzer.config;
+    if !fuzzer.tsukamoto.is_empty() {
+        Err(FuzzyError::InvalidParameters("Tsukamoto outputs have no FCL equivalent".to_string()))?
+    }
+    let unsupported = |what: String| FuzzyError::InvalidParameters(format!("{} has no FCL equivalent", what));
+    let and = match config.and {
+        TNorm::Min => "MIN",
+        TNorm::Product => "PROD",
+        TNorm::Lukasiewicz => "BDIF",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    };
+    let or = match config.or {
+        SNorm::Max => "MAX",
+        SNorm::ProbabilisticSum => "ASUM",
+        SNorm::Lukasiewicz => "BSUM",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    };
+    let act = match config.implication {
+        Implication::Min => "MIN",
+        Implication::Product => "PROD",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    };
+    let accu = match config.aggregation {
+        Aggregation::Max => "MAX",
+        Aggregation::BoundedSum => "BSUM",
+        Aggregation::NormalizedSum => "NSUM",
+        Aggregation::ProbabilisticOr => "PROBOR",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    };
+
+    let sorted = |map: &HashMap<Category, FuzzySet>| {
+        let mut keys = map.keys().cloned().collect::<Vec<Category>>();
+        keys.sort();
+        keys
+    };
+    let inputs = sorted(&fuzzer.categories);
+    let outputs = sorted(&fuzzer.outputs);
+    let mut sugeno = fuzzer.sugeno.keys().cloned().collect::<Vec<Category>>();
+    sugeno.sort();
+
+    let mut out = String::new();
+    writeln!(out, "FUNCTION_BLOCK {}\n", quote(name)).unwrap();
+    writeln!(out, "VAR_INPUT").unwrap();
+    for category in inputs.iter() {
+        writeln!(out, "    {} : REAL;", quote(category)).unwrap();
+    }
+    writeln!(out, "END_VAR\n\nVAR_OUTPUT").unwrap();
+    for category in outputs.iter().chain(sugeno.iter()) {
+        writeln!(out, "    {} : REAL;", quote(category)).unwrap();
+    }
+    writeln!(out, "END_VAR\n").unwrap();
+
+    for category in inputs.iter() {
+        writeln!(out, "FUZZIFY {}", quote(category)).unwrap();
+        write_terms(&mut out, category, &fuzzer.categories[category])?;
+        writeln!(out, "END_FUZZIFY\n").unwrap();
+    }
+    for category in outputs.iter() {
+        let defuzzifier = fuzzer.defuzzifiers.get(category).unwrap_or(&config.defuzzifier);
+        let method = match defuzzifier {
+            Defuzzifier::Centroid => "COG",
+            Defuzzifier::Bisector => "COA",
+            Defuzzifier::MeanOfMaximum => "MM",
+            Defuzzifier::FirstOfMaxima => "LM",
+            Defuzzifier::LastOfMaxima => "RM",
+            // COGS is the centroid of singletons, only Sugeno outputs are written with it.
+            other => Err(unsupported(format!("{:?}", other)))?
+        };
+        writeln!(out, "DEFUZZIFY {}", quote(category)).unwrap();
+        write_terms(&mut out, category, &fuzzer.outputs[category])?;
+        writeln!(out, "    METHOD : {};\nEND_DEFUZZIFY\n", method).unwrap();
+    }
+    for category in sugeno.iter() {
+        let mut terms = fuzzer.sugeno[category].terms().collect::<Vec<_>>();
+        terms.sort_by(|a, b| a.0.cmp(b.0));
+        writeln!(out, "DEFUZZIFY {}", quote(category)).unwrap();
+        for (term, function) in terms {
+            match function {
+                SugenoTerm::Constant(value) =>
+                    writeln!(out, "    TERM {} := {};", quote(term), value).unwrap(),
+                SugenoTerm::Linear(_, _) => Err(unsupported(format!("linear term {}", term)))?
+            }
+        }
+        writeln!(out, "    METHOD : COGS;\nEND_DEFUZZIFY\n").unwrap();
+    }
+
+    writeln!(out, "RULEBLOCK rules").unwrap();
+    writeln!(out, "    AND : {};\n    OR : {};\n    ACT : {};\n    ACCU : {};", and, or, act, accu).unwrap();
+    for (i, rule) in fuzzer.rules.iter().enumerate() {
+        writeln!(out, "    RULE {} : {};", i+1, rule).unwrap();
+    }
+    writeln!(out, "END_RULEBLOCK\n\nEND_FUNCTION_BLOCK").unwrap();
+    Ok(out)
+}
+
+fn write_terms(
+    out: &mut String,
+    category: &str,
+    set: &FuzzySet
+) -> FuzzyResult<()> {
+    if set.period.is_some() {
+        Err(FuzzyError::InvalidParameters(format!("periodic set {} has no FCL equivalent", category)))?
+    }
+    // RANGE is read back with the default policy.
+    if set.universe.is_some() && set.out_of_range != RangePolicy::Clamp {
+        Err(FuzzyError::InvalidParameters(format!("{:?} out of range of {} has no FCL equivalent", set.out_of_range, category)))?
+    }
+    let mut terms = set.terms().collect::<Vec<_>>();
+    terms.sort_by(|a, b| a.0.cmp(b.0));
+    for (term, function) in terms {
+        let shape = match function {
+            MembershipFunction::Gaussian { mean, sigma } => format!("gauss {} {}", mean, sigma),
+            MembershipFunction::Bell { a, b, c } => format!("gbell {} {} {}", a, b, c),
+            MembershipFunction::Sigmoid { a, c } => format!("sigm {} {}", a, c),
+            other => other.points()
+                .iter()
+                .map(|(x, y)| format!("({}, {})", x, y))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        writeln!(out, "    TERM {} := {};", quote(term), shape).unwrap();
+    }
+    if let Some((min, max)) = set.bounds() {
+        writeln!(out, "    RANGE := ({} .. {});", min, max).unwrap();
+    }
+    Ok(())
+}
+
+/// Replaces comments with spaces, so that positions of the remaining text don't change.
+fn strip_comments(
+    source: &str
+) -> String {
+    let chars = source.chars().collect::<Vec<char>>();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let end = match (chars[i], chars.get(i+1)) {
+            ('(', Some('*')) => Some(&['*', ')'][..]),
+            ('/', Some('*')) => Some(&['*', '/'][..]),
+            ('/', Some('/')) => Some(&['\n'][..]),
+            _ => None
+        };
+        let Some(end) = end else {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        };
+        // Skip the opening pair, then everything up to and including the terminator.
+        let mut j = i+2;
+        while j < chars.len() && !chars[j..].starts_with(end) {
+            j += 1;
+        }
+        let j = (j+end.len()).min(chars.len());
+        out.extend(chars[i..j].iter().map(|c| if c.is_whitespace() { *c } else { ' ' }));
+        i = j;
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Symbol(&'static str)
+}
+
+/// Token with its line, column and byte offset.
+type Spanned = (Token, usize, usize, usize);
+
+fn tokenize(
+    source: &str
+) -> FuzzyResult<Vec<Spanned>> {
+    let mut tokens = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let token = if c.is_whitespace() {
+            None
+        } else if c == '"' {
+            chars.next();
+            column += 1;
+            let mut text = String::new();
+            loop {
+                match chars.peek() {
+                    Some(&(_, '"')) => break,
+                    Some(&(_, c)) if c != '\n' => {
+                        column += 1;
+                        chars.next();
+                        // Backslash escapes the next character, as in rules.
+                        let c = match (c, chars.peek()) {
+                            ('\\', Some(&(_, next))) if next != '\n' => {
+                                column += 1;
+                                chars.next();
+                                next
+                            },
+                            _ => c
+                        };
+                        text.push(c);
+                    },
+                    _ => Err(FuzzyError::Parse {
+                        line: start_line,
+                        column: start_column,
+                        message: "unterminated quote".to_string()
+                    })?
+                }
+            }
+            Some(Token::Quoted(text))
+        } else if c.is_ascii_alphanumeric() || "_.-+".contains(c) {
+            let mut text = c.to_string();
+            chars.next();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || "_.-+".contains(c)) {
+                    break;
+                }
+                text.push(c);
+                column += 1;
+                chars.next();
+            }
+            tokens.push((Token::Word(text), start_line, start_column, offset));
+            column += 1;
+            continue;
+        } else {
+            let symbol = [":=", ":", ";", "(", ")", ","]
+                .iter()
+                .find(|symbol| source[offset..].starts_with(*symbol))
+                .ok_or(FuzzyError::Parse {
+                    line,
+                    column,
+                    message: format!("unexpected character '{}'", c)
+                })?;
+            if symbol.len() == 2 {
+                chars.next();
+                column += 1;
+            }
+            Some(Token::Symbol(symbol))
+        };
+        if let Some(token) = token {
+            tokens.push((token, start_line, start_column, offset));
+        }
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+        chars.next();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Spanned>,
+    position: usize
+}
+
+impl Parser<'_> {
+    fn peek(
+        &self
+    ) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _, _, _)| token)
+    }
+
+    fn is(
+        &self,
+        keyword: &str
+    ) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) => word.eq_ignore_ascii_case(keyword),
+            Some(Token::Symbol(symbol)) => *symbol == keyword,
+            _ => false
+        }
+    }
+
+    fn error(
+        &self,
+        message: &str
+    ) -> FuzzyError {
+        let (line, column) = match self.tokens.get(self.position) {
+            Some((_, line, column, _)) => (*line, *column),
+            None => (self.source.lines().count().max(1),
+                     self.source.lines().last().map(|l| l.chars().count()).unwrap_or(0)+1)
+        };
+        let found = match self.peek() {
+            Some(Token::Word(word)) => format!("'{}'", word),
+            Some(Token::Quoted(text)) => format!("\"{}\"", text),
+            Some(Token::Symbol(symbol)) => format!("'{}'", symbol),
+            None => "end of input".to_string()
+        };
+        FuzzyError::Parse { line, column, message: format!("{}, found {}", message, found) }
+    }
+
+    fn expect(
+        &mut self,
+        keyword: &str
+    ) -> FuzzyResult<()> {
+        if !self.is(keyword) {
+            Err(self.error(&format!("expected {}", keyword)))?
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn name(
+        &mut self
+    ) -> FuzzyResult<String> {
+        let name = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            Some(Token::Quoted(text)) => text.clone(),
+            _ => Err(self.error("expected name"))?
+        };
+        self.position += 1;
+        Ok(name)
+    }
+
+    fn number(
+        &mut self
+    ) -> FuzzyResult<f64> {
+        let number = match self.peek() {
+            Some(Token::Word(word)) => word.parse::<f64>().ok(),
+            _ => None
+        }.ok_or_else(|| self.error("expected number"))?;
+        self.position += 1;
+        Ok(number)
+    }
+
+    /// Skips tokens up to and including the next semicolon.
+    fn skip_statement(
+        &mut self
+    ) -> FuzzyResult<()> {
+        while !self.is(";") {
+            if self.peek().is_none() {
+                Err(self.error("expected ;"))?
+            }
+            self.position += 1;
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Reads `KEY : VALUE;` setting and returns upper-cased value.
+    fn setting(
+        &mut self
+    ) -> FuzzyResult<String> {
+        self.position += 1;
+        self.expect(":")?;
+        let value = self.name()?.to_uppercase();
+        self.expect(";")?;
+        Ok(value)
+    }
+
+    fn function_block(
+        &mut self
+    ) -> FuzzyResult<Fuzzer> {
+        self.expect("FUNCTION_BLOCK")?;
+        if !self.is("VAR_INPUT") && !self.is("VAR_OUTPUT") {
+            self.name()?;
+        }
+        let mut inputs = HashSet::new();
+        let mut outputs = HashSet::new();
+        let mut fuzzer = Fuzzer::new();
+        // Operators of the first rule block, later ones have to match.
+        let mut operators: Option<Vec<String>> = None;
+        let mut config = FuzzerConfig::new();
+        while !self.is("END_FUNCTION_BLOCK") {
+            if self.is("VAR_INPUT") || self.is("VAR_OUTPUT") {
+                let declared = if self.is("VAR_INPUT") { &mut inputs } else { &mut outputs };
+                self.position += 1;
+                while !self.is("END_VAR") {
+                    declared.insert(self.name()?);
+                    self.expect(":")?;
+                    self.skip_statement()?;
+                }
+                self.position += 1;
+            } else if self.is("FUZZIFY") {
+                self.position += 1;
+                let category = self.declared(&inputs)?;
+                let (set, singletons) = self.terms("END_FUZZIFY", &mut None)?;
+                if !singletons.is_empty() {
+                    Err(FuzzyError::InvalidParameters(format!("singleton terms of input {}", category)))?
+                }
+                fuzzer = fuzzer.fuzzify(category, set);
+            } else if self.is("DEFUZZIFY") {
+                self.position += 1;
+                let category = self.declared(&outputs)?;
+                let mut method = None;
+                let (set, singletons) = self.terms("END_DEFUZZIFY", &mut method)?;
+                if singletons.is_empty() {
+                    let defuzzifier = match method.as_deref() {
+                        None | Some("COG") => Defuzzifier::Centroid,
+                        Some("COA") => Defuzzifier::Bisector,
+                        Some("MM") => Defuzzifier::MeanOfMaximum,
+                        Some("LM") => Defuzzifier::FirstOfMaxima,
+                        Some("RM") => Defuzzifier::LastOfMaxima,
+                        Some("COGS") => Err(FuzzyError::InvalidParameters(format!("COGS needs singleton terms in output {}", category)))?,
+                        Some(other) => Err(FuzzyError::InvalidParameters(format!("unknown method {}", other)))?
+                    };
+                    fuzzer = fuzzer.defuzzify_with(category, set, defuzzifier);
+                } else if set.terms().next().is_some() {
+                    Err(FuzzyError::InvalidParameters(format!("singletons mixed with sets in output {}", category)))?
+                } else {
+                    let set = singletons.into_iter()
+                        .fold(SugenoSet::new(), |set, (term, value)| set.constant(term, value));
+                    fuzzer = fuzzer.sugeno(category, set);
+                }
+            } else if self.is("RULEBLOCK") {
+                self.position += 1;
+                self.name()?;
+                let mut current = vec!["MIN".to_string(), "MAX".to_string(), "MIN".to_string(), "MAX".to_string()];
+                let start = self.position;
+                while !self.is("END_RULEBLOCK") {
+                    let index = ["AND", "OR", "ACT", "ACCU"].iter().position(|k| self.is(k));
+                    if let Some(index) = index {
+                        current[index] = self.setting()?;
+                    } else if self.is("RULE") {
+                        fuzzer = fuzzer.rule(self.rule()?);
+                    } else {
+                        Err(self.error("expected rule block item"))?
+                    }
+                }
+                self.position += 1;
+                match &operators {
+                    Some(operators) if *operators != current => {
+                        self.position = start;
+                        Err(self.error("operators differ from previous rule block"))?
+                    },
+                    _ => operators = Some(current.clone())
+                }
+                config = config
+                    .and(match current[0].as_str() {
+                        "MIN" => TNorm::Min,
+                        "PROD" => TNorm::Product,
+                        "BDIF" => TNorm::Lukasiewicz,
+                        other => Err(FuzzyError::InvalidParameters(format!("unknown AND {}", other)))?
+                    })
+                    .or(match current[1].as_str() {
+                        "MAX" => SNorm::Max,
+                        "ASUM" => SNorm::ProbabilisticSum,
+                        "BSUM" => SNorm::Lukasiewicz,
+                        other => Err(FuzzyError::InvalidParameters(format!("unknown OR {}", other)))?
+                    })
+                    .implication(match current[2].as_str() {
+                        "MIN" => Implication::Min,
+                        "PROD" => Implication::Product,
+                        other => Err(FuzzyError::InvalidParameters(format!("unknown ACT {}", other)))?
+                    })
+                    .aggregation(match current[3].as_str() {
+                        "MAX" => Aggregation::Max,
+                        "BSUM" => Aggregation::BoundedSum,
+                        "NSUM" => Aggregation::NormalizedSum,
+                        "PROBOR" => Aggregation::ProbabilisticOr,
+                        other => Err(FuzzyError::InvalidParameters(format!("unknown ACCU {}", other)))?
+                    });
+            } else {
+                Err(self.error("expected block"))?
+            }
+        }
+        Ok(fuzzer.config(config))
+    }
+
+    /// Reads variable name, which has to be declared in VAR_INPUT or VAR_OUTPUT.
+    fn declared(
+        &mut self,
+        declared: &HashSet<String>
+    ) -> FuzzyResult<String> {
+        if let Some(Token::Word(name)) | Some(Token::Quoted(name)) = self.peek() {
+            if !declared.contains(name) {
+                Err(self.error("undeclared variable"))?
+            }
+        }
+        self.name()
+    }
+
+    /// Reads contents of FUZZIFY or DEFUZZIFY block. Singletons are returned separately.
+    fn terms(
+        &mut self,
+        end: &str,
+        method: &mut Option<String>
+    ) -> FuzzyResult<(FuzzySet, Vec<(String, f64)>)> {
+        let mut set = FuzzySet::new();
+        let mut singletons = Vec::new();
+        while !self.is(end) {
+            if self.is("TERM") {
+                self.position += 1;
+                let term = self.name()?;
+                self.expect(":=")?;
+                if self.is("(") {
+                    let mut points = Vec::new();
+                    while self.is("(") {
+                        self.position += 1;
+                        let x = self.number()?;
+                        self.expect(",")?;
+                        let y = self.number()?;
+                        self.expect(")")?;
+                        points.push((x, y));
+                    }
+                    set = set.term(term, points)?;
+                } else if let Ok(value) = self.number() {
+                    singletons.push((term, value));
+                } else {
+                    let shape = self.name()?.to_lowercase();
+                    let function = match shape.as_str() {
+                        "trian" => MembershipFunction::Triangular {
+                            a: self.number()?, b: self.number()?, c: self.number()?
+                        },
+                        "trape" => MembershipFunction::Trapezoidal {
+                            a: self.number()?, b: self.number()?, c: self.number()?, d: self.number()?
+                        },
+                        "gauss" => MembershipFunction::Gaussian { mean: self.number()?, sigma: self.number()? },
+                        "gbell" => MembershipFunction::Bell {
+                            a: self.number()?, b: self.number()?, c: self.number()?
+                        },
+                        "sigm" => MembershipFunction::Sigmoid { a: self.number()?, c: self.number()? },
+                        _ => {
+                            self.position -= 1;
+                            Err(self.error("expected membership function"))?
+                        }
+                    };
+                    set = set.term(term, function)?;
+                }
+                self.expect(";")?;
+            } else if self.is("METHOD") && end == "END_DEFUZZIFY" {
+                *method = Some(self.setting()?);
+            } else if self.is("RANGE") {
+                self.position += 1;
+                self.expect(":=")?;
+                self.expect("(")?;
+                let start = self.position;
+                // `min .. max` may be a single word, as dots are part of numbers.
+                let mut text = String::new();
+                while let Some(Token::Word(word)) = self.peek() {
+                    text.push_str(word);
+                    self.position += 1;
+                }
+                let bounds = text.split_once("..")
+                    .and_then(|(min, max)| Some((min.parse::<f64>().ok()?, max.parse::<f64>().ok()?)));
+                set = match bounds {
+                    Some((min, max)) if min < max => set.universe(min, max)?,
+                    _ => {
+                        self.position = start;
+                        Err(self.error("expected min .. max"))?
+                    }
+                };
+                self.expect(")")?;
+                self.expect(";")?;
+            } else if self.is("DEFAULT") || self.is("LOCK") || self.is("ACCU") {
+                self.skip_statement()?;
+            } else {
+                Err(self.error("expected TERM"))?
+            }
+        }
+        self.position += 1;
+        Ok((set, singletons))
+    }
+
+    /// `RULE name : rule;`, where the rule is handed to `FuzzyRule::from_str`.
+    fn rule(
+        &mut self
+    ) -> FuzzyResult<FuzzyRule> {
+        self.position += 1;
+        self.name()?;
+        self.expect(":")?;
+        let (_, line, column, offset) = self.tokens.get(self.position)
+            .cloned()
+            .ok_or_else(|| self.error("expected rule"))?;
+        self.skip_statement()?;
+        let end = self.tokens[self.position-1].3;
+        self.source[offset..end].parse::<FuzzyRule>().map_err(|error| match error {
+            // Positions are relative to the rule.
+            FuzzyError::Parse { line: l, column: c, message } => FuzzyError::Parse {
+                line: line+l-1,
+                column: if l == 1 { column+c-1 } else { c },
+                message
+            },
+            other => other
+        })
+    }
+}
+
+#[test]
+fn test_fcl(
+) -> FuzzyResult<()> {
+    use super::membership::very;
+    let source = "
+        (* Tipper from the standard, in jFuzzyLogic flavour. *)
+        FUNCTION_BLOCK tipper
+        VAR_INPUT
+            service : REAL;
+            food : REAL;
+        END_VAR
+        VAR_OUTPUT
+            tip : REAL;
+        END_VAR
+        FUZZIFY service
+            TERM poor := gauss 0 1;
+            TERM good := (2.5, 0) (5, 1) (7.5, 0);
+            TERM excellent := trape 7.5 9 10 10;
+        END_FUZZIFY
+        FUZZIFY food
+            TERM rancid := (0, 1) (1, 1) (3, 0);   // Comment.
+            TERM delicious := (7, 0) (9, 1);
+        END_FUZZIFY
+        DEFUZZIFY tip
+            TERM cheap := (0, 0) (5, 1) (10, 0);
+            TERM average := (10, 0) (15, 1) (20, 0);
+            TERM generous := (20, 0) (25, 1) (30, 0);
+            METHOD : COG;
+            DEFAULT := 0;
+            RANGE := (0 .. 30);
+        END_DEFUZZIFY
+        RULEBLOCK No1
+            AND : PROD;
+            OR : ASUM;
+            ACCU : MAX;
+            RULE 1 : IF service IS poor OR food IS rancid THEN tip IS cheap;
+            RULE 2 : IF service IS good THEN tip IS average;
+            RULE 3 : IF service IS excellent AND food IS delicious THEN tip IS generous WITH 0.5;
+        END_RULEBLOCK
+        END_FUNCTION_BLOCK
+    ";
+    let fuzzer = from_fcl(source)?;
+    let values = crate::values! { "service" => 5.0; "food" => 5.0 };
+    assert!((fuzzer.evaluate(&values)?["tip"]-15.0).abs() < 1e-3);
+    assert_eq!(fuzzer.outputs["tip"].bounds(), Some((0.0, 30.0)));
+
+    // Round trip.
+    let fuzzer = fuzzer
+        .sugeno("bonus", SugenoSet::new().constant("none", 0.0).constant("some", 1.0).constant("say \"hi\"", 0.5))
+        .rule(crate::unit!("food" => very("delicious"); "bonus" => "some"))
+        .rule(crate::unit!("food" => "rancid"; "bonus" => "say \"hi\""))
+        .rule(crate::unit!("food" => !"delicious"; "bonus" => "none"));
+    let exported = to_fcl(&fuzzer, "tipper")?;
+    let imported = from_fcl(&exported)?;
+    assert_eq!(to_fcl(&imported, "tipper")?, exported);
+    assert_eq!(imported.rules, fuzzer.rules);
+    for (service, food) in [(1.0, 2.0), (5.0, 5.0), (8.0, 9.0), (9.5, 7.5)] {
+        let values = crate::values! { "service" => service; "food" => food };
+        let expected = fuzzer.evaluate(&values)?;
+        for (category, y) in imported.evaluate(&values)? {
+            assert!((y-expected[&category]).abs() < 1e-9);
+        }
+    }
+
+    let error = |line, column, message: &str| FuzzyError::Parse { line, column, message: message.to_string() };
+    let invalid = "FUNCTION_BLOCK x\nVAR_INPUT a : REAL; END_VAR\nFUZZIFY b\nEND_FUZZIFY\nEND_FUNCTION_BLOCK";
+    assert_eq!(from_fcl(invalid).err(), Some(error(3, 9, "undeclared variable, found 'b'")));
+    let invalid = "FUNCTION_BLOCK x\nRULEBLOCK r\n  RULE 1 : IF a IS THEN b IS c;\nEND_RULEBLOCK\nEND_FUNCTION_BLOCK";
+    assert_eq!(from_fcl(invalid).err(), Some(error(3, 20, "expected term, found 'THEN'")));
+    let typo = "FUNCTION_BLOCK x
+VAR_INPUT a : REAL; END_VAR
+VAR_OUTPUT b : REAL; END_VAR
+FUZZIFY a TERM x := (0, 0) (1, 1); END_FUZZIFY
+DEFUZZIFY b TERM y := (0, 0) (1, 1); METHOD : COG; END_DEFUZZIFY
+RULEBLOCK r
+  RULE 1 : IF a IS typo THEN b IS y;
+END_RULEBLOCK
+END_FUNCTION_BLOCK";
+    assert_eq!(from_fcl(typo).err(), Some(FuzzyError::InvalidRules(vec![(0, FuzzyError::InvalidTerm("typo".to_string()))])));
+
+    // COGS is defined for singletons only.
+    let cogs = source.replace("METHOD : COG;", "METHOD : COGS;");
+    assert_eq!(from_fcl(&cogs).err(), Some(FuzzyError::InvalidParameters("COGS needs singleton terms in output tip".to_string())));
+    let height = fuzzer.defuzzify_with("tip", imported.outputs["tip"].clone(), Defuzzifier::Height);
+    assert_eq!(to_fcl(&height, "tipper"), Err(FuzzyError::InvalidParameters("Height has no FCL equivalent".to_string())));
+    let periodic = Fuzzer::new().fuzzify("heading", FuzzySet::new()
+        .periodic(360.0)?
+        .term("north", vec![(-30.0, 0.0), (0.0, 1.0), (30.0, 0.0)])?);
+    assert_eq!(to_fcl(&periodic, "compass"),
+               Err(FuzzyError::InvalidParameters("periodic set heading has no FCL equivalent".to_string())));
+    let food = imported.categories["food"].clone().universe(0.0, 10.0)?.out_of_range(RangePolicy::Zero);
+    let strict = imported.fuzzify("food", food);
+    assert_eq!(to_fcl(&strict, "tipper"),
+               Err(FuzzyError::InvalidParameters("Zero out of range of food has no FCL equivalent".to_string())));
+    Ok(())
+}
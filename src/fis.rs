@@ -0,0 +1,728 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::fuzz::{Antecedent, Fuzzer, FuzzerConfig, FuzzyRule};
+use super::set::{FuzzySet, RangePolicy};
+use super::sugeno::{SugenoSet, SugenoTerm};
+use super::membership::{MembershipFunction, SAMPLES};
+use super::ops::{TNorm, SNorm, Implication, Aggregation};
+use super::defuzz::Defuzzifier;
+use super::common::{Category, FuzzyError, FuzzyResult, FuzzyIdent, Term};
+
+/// Reads MATLAB Fuzzy Logic Toolbox `.fis` file.
+///
+/// trimf, trapmf, gaussmf, gbellmf, sigmf, smf, zmf and pimf map onto equivalent functions,
+/// linsmf and linzmf onto polylines. gauss2mf, dsigmf and psigmf have no equivalent and are
+/// sampled over the variable's range. Rules listing several outputs are split into one rule
+/// per output. Range of inputs and Mamdani outputs becomes universe of their sets.
+/// Sugeno systems have to use weighted average defuzzification.
+pub fn from_fis(
+    source: &str
+) -> FuzzyResult<Fuzzer> {
+    let sections = sections(source)?;
+    let system = sections.iter()
+        .find(|section| section.name == "System")
+        .ok_or(FuzzyError::Parse { line: 1, column: 1, message: "missing [System] section".to_string() })?;
+    let sugeno = match system.text("Type")?.to_lowercase().as_str() {
+        "mamdani" => false,
+        "sugeno" => true,
+        _ => Err(system.error("Type", "expected 'mamdani' or 'sugeno'"))?
+    };
+    let config = FuzzerConfig::new()
+        .and(match system.text("AndMethod")?.as_str() {
+            "min" => TNorm::Min,
+            "prod" => TNorm::Product,
+            _ => Err(system.error("AndMethod", "expected 'min' or 'prod'"))?
+        })
+        .or(match system.text("OrMethod")?.as_str() {
+            "max" => SNorm::Max,
+            "probor" => SNorm::ProbabilisticSum,
+            _ => Err(system.error("OrMethod", "expected 'max' or 'probor'"))?
+        })
+        .implication(match system.text("ImpMethod")?.as_str() {
+            "min" => Implication::Min,
+            "prod" => Implication::Product,
+            _ => Err(system.error("ImpMethod", "expected 'min' or 'prod'"))?
+        })
+        // Sum is normalized, which doesn't change defuzzified value.
+        .aggregation(match system.text("AggMethod")?.as_str() {
+            "max" => Aggregation::Max,
+            "sum" => Aggregation::NormalizedSum,
+            "probor" => Aggregation::ProbabilisticOr,
+            _ => Err(system.error("AggMethod", "expected 'max', 'sum' or 'probor'"))?
+        })
+        .defuzzifier(match system.text("DefuzzMethod")?.as_str() {
+            "centroid" => Defuzzifier::Centroid,
+            "bisector" => Defuzzifier::Bisector,
+            "mom" => Defuzzifier::MeanOfMaximum,
+            "som" => Defuzzifier::SmallestOfMaximum,
+            "lom" => Defuzzifier::LargestOfMaximum,
+            "wtaver" if sugeno => Defuzzifier::Centroid,
+            _ => Err(system.error("DefuzzMethod", "unsupported method"))?
+        });
+    let mut fuzzer = Fuzzer::new().config(config);
+
+    let variables = |prefix: &str, count: &str| -> FuzzyResult<Vec<&Section>> {
+        let count = system.number(count)? as usize;
+        (1..=count)
+            .map(|i| sections.iter()
+                .find(|section| section.name == format!("{}{}", prefix, i))
+                .ok_or(system.error("", &format!("missing [{}{}] section", prefix, i))))
+            .collect()
+    };
+    let inputs = variables("Input", "NumInputs")?;
+    let outputs = variables("Output", "NumOutputs")?;
+    let input_names = inputs.iter()
+        .map(|section| section.text("Name"))
+        .collect::<FuzzyResult<Vec<Category>>>()?;
+
+    // Terms of every variable in file order, referenced by rules.
+    let mut input_terms = Vec::new();
+    for section in inputs.iter() {
+        let (name, terms) = section.terms()?;
+        let mut set = section.universe()?;
+        for (term, function) in terms.iter() {
+            set = set.term(term.clone(), function.clone())?;
+        }
+        input_terms.push(terms.into_iter().map(|(term, _)| term).collect::<Vec<Term>>());
+        fuzzer = fuzzer.fuzzify(name, set);
+    }
+    let mut output_terms = Vec::new();
+    let mut output_names = Vec::new();
+    for section in outputs.iter() {
+        if sugeno {
+            let name = section.text("Name")?;
+            let mut set = SugenoSet::new();
+            let mut terms = Vec::new();
+            for i in 1..=section.number("NumMFs")? as usize {
+                let key = format!("MF{}", i);
+                let (term, kind, params) = section.mf(&key)?;
+                set = match kind.as_str() {
+                    "constant" if params.len() == 1 => set.constant(term.clone(), params[0]),
+                    "linear" if params.len() == input_names.len()+1 => set.linear(
+                        term.clone(),
+                        input_names.iter().cloned().zip(params.iter().cloned()).collect(),
+                        params[input_names.len()]
+                    ),
+                    _ => Err(section.error(&key, "expected constant or linear function"))?
+                };
+                terms.push(term);
+            }
+            output_terms.push(terms);
+            output_names.push(name.clone());
+            fuzzer = fuzzer.sugeno(name, set);
+        } else {
+            let (name, terms) = section.terms()?;
+            let mut set = section.universe()?;
+            for (term, function) in terms.iter() {
+                set = set.term(term.clone(), function.clone())?;
+            }
+            output_terms.push(terms.into_iter().map(|(term, _)| term).collect::<Vec<Term>>());
+            output_names.push(name.clone());
+            fuzzer = fuzzer.defuzzify(name, set);
+        }
+    }
+
+    let Some(rules) = sections.iter().find(|section| section.name == "Rules") else {
+        return Ok(fuzzer)
+    };
+    for (line, text) in rules.lines.iter() {
+        let error = |message: &str| FuzzyError::Parse { line: *line, column: 1, message: message.to_string() };
+        // "1 -2, 1 (0.5) : 1"
+        let (clauses, rest) = text.split_once(',').ok_or_else(|| error("expected ','"))?;
+        let (consequents, rest) = rest.split_once('(').ok_or_else(|| error("expected '('"))?;
+        let (weight, connective) = rest.split_once(')').ok_or_else(|| error("expected ')'"))?;
+        let connective = connective.trim().trim_start_matches(':').trim();
+        let indices = |text: &str, count: usize| -> FuzzyResult<Vec<i64>> {
+            let indices = text.split_whitespace()
+                .map(|index| index.parse::<f64>().map(|i| i as i64))
+                .collect::<Result<Vec<i64>, _>>()
+                .map_err(|_| error("expected term index"))?;
+            if indices.len() != count {
+                Err(error(&format!("expected {} term indices", count)))?
+            }
+            Ok(indices)
+        };
+        let term = |terms: &[Term], index: i64| -> FuzzyResult<Term> {
+            terms.get(index.unsigned_abs() as usize-1)
+                .cloned()
+                .ok_or_else(|| error(&format!("term index {} out of range", index)))
+        };
+        let mut items = Vec::new();
+        for (i, index) in indices(clauses, inputs.len())?.into_iter().enumerate() {
+            if index == 0 {
+                continue;
+            }
+            let clause = Antecedent::Is((input_names[i].clone(), term(&input_terms[i], index)?.into()));
+            items.push(if index < 0 { !clause } else { clause });
+        }
+        let antecedent = match (items.len(), connective) {
+            (1, "1" | "2") => items.pop().unwrap(),
+            (_, "1") => Antecedent::And(items),
+            (_, "2") => Antecedent::Or(items),
+            _ => Err(error("expected connective 1 or 2"))?
+        };
+        let weight = weight.trim().parse::<f64>().map_err(|_| error("expected weight"))?;
+        for (i, index) in indices(consequents, outputs.len())?.into_iter().enumerate() {
+            if index < 0 {
+                Err(error("negated consequents are not supported"))?
+            }
+            if index > 0 {
+                let consequent = (output_names[i].clone(), term(&output_terms[i], index)?.into());
+                fuzzer = fuzzer.rule(FuzzyRule::new(antecedent.clone(), consequent).weight(weight));
+            }
+        }
+    }
+    Ok(fuzzer)
+}
+
+/// Reads `.fis` file from disk, see `from_fis`.
+pub fn read_fis(
+    path: impl AsRef<std::path::Path>
+) -> FuzzyResult<Fuzzer> {
+    from_fis(&std::fs::read_to_string(path)?)
+}
+
+/// Writes Fuzzer in MATLAB `.fis` format. Variables and terms are sorted by name.
+///
+/// Polylines are written as trimf, trapmf, linsmf or linzmf when they have matching shape,
+/// otherwise writing fails, as do hedges, nested premises, periodic sets, out of range policies
+/// other than `RangePolicy::Clamp` and Tsukamoto outputs.
+/// Shoulders are written as trapmf, which differs from polyline outside of the variable's range.
+pub fn to_fis(
+    fuzzer: &Fuzzer,
+    name: &str
+) -> FuzzyResult<String> {
+    let config = &fuzzer.config;
+    let unsupported = |what: String| FuzzyError::InvalidParameters(format!("{} has no .fis equivalent", what));
+    if !fuzzer.tsukamoto.is_empty() {
+        Err(unsupported("Tsukamoto output".to_string()))?
+    }
+    let sugeno = !fuzzer.sugeno.is_empty();
+    if sugeno && !fuzzer.outputs.is_empty() {
+        Err(unsupported("mix of Mamdani and Sugeno outputs".to_string()))?
+    }
+    let sorted = |keys: Vec<&String>| {
+        let mut keys = keys.into_iter().cloned().collect::<Vec<String>>();
+        keys.sort();
+        keys
+    };
+    let inputs = sorted(fuzzer.categories.keys().collect());
+    let outputs = if sugeno {
+        sorted(fuzzer.sugeno.keys().collect())
+    } else {
+        sorted(fuzzer.outputs.keys().collect())
+    };
+    let set_terms = |set: &FuzzySet| sorted(set.terms().map(|(term, _)| term).collect());
+    let input_terms = inputs.iter()
+        .map(|category| set_terms(&fuzzer.categories[category]))
+        .collect::<Vec<Vec<Term>>>();
+    let output_terms = outputs.iter()
+        .map(|category| if sugeno {
+            sorted(fuzzer.sugeno[category].terms().map(|(term, _)| term).collect())
+        } else {
+            set_terms(&fuzzer.outputs[category])
+        })
+        .collect::<Vec<Vec<Term>>>();
+
+    let defuzzifier = |category: &Category| fuzzer.defuzzifiers.get(category).unwrap_or(&config.defuzzifier);
+    let method = if sugeno {
+        "wtaver"
+    } else {
+        // Method is global in .fis files.
+        let method = outputs.first().map(defuzzifier).unwrap_or(&config.defuzzifier);
+        if outputs.iter().any(|category| defuzzifier(category) != method) {
+            Err(unsupported("per output defuzzifier".to_string()))?
+        }
+        match method {
+            Defuzzifier::Centroid => "centroid",
+            Defuzzifier::Bisector => "bisector",
+            Defuzzifier::MeanOfMaximum => "mom",
+            Defuzzifier::SmallestOfMaximum => "som",
+            Defuzzifier::LargestOfMaximum => "lom",
+            other => Err(unsupported(format!("{:?}", other)))?
+        }
+    };
+
+    let mut out = String::new();
+    writeln!(out, "[System]").unwrap();
+    writeln!(out, "Name='{}'", name).unwrap();
+    writeln!(out, "Type='{}'", if sugeno { "sugeno" } else { "mamdani" }).unwrap();
+    writeln!(out, "Version=2.0").unwrap();
+    writeln!(out, "NumInputs={}", inputs.len()).unwrap();
+    writeln!(out, "NumOutputs={}", outputs.len()).unwrap();
+    writeln!(out, "NumRules={}", fuzzer.rules.len()).unwrap();
+    writeln!(out, "AndMethod='{}'", match config.and {
+        TNorm::Min => "min",
+        TNorm::Product => "prod",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    }).unwrap();
+    writeln!(out, "OrMethod='{}'", match config.or {
+        SNorm::Max => "max",
+        SNorm::ProbabilisticSum => "probor",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    }).unwrap();
+    writeln!(out, "ImpMethod='{}'", match config.implication {
+        Implication::Min => "min",
+        Implication::Product => "prod",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    }).unwrap();
+    writeln!(out, "AggMethod='{}'", match config.aggregation {
+        Aggregation::Max => "max",
+        Aggregation::NormalizedSum => "sum",
+        Aggregation::ProbabilisticOr => "probor",
+        ref other => Err(unsupported(format!("{:?}", other)))?
+    }).unwrap();
+    writeln!(out, "DefuzzMethod='{}'", method).unwrap();
+
+    for (i, category) in inputs.iter().enumerate() {
+        writeln!(out, "\n[Input{}]", i+1).unwrap();
+        write_set(&mut out, category, &fuzzer.categories[category], &input_terms[i])?;
+    }
+    for (i, category) in outputs.iter().enumerate() {
+        writeln!(out, "\n[Output{}]", i+1).unwrap();
+        if sugeno {
+            let set = &fuzzer.sugeno[category];
+            // Range spans values of the consequents over ranges of the inputs.
+            let (from, to) = set.terms()
+                .map(|(_, function)| match function {
+                    SugenoTerm::Constant(value) => (*value, *value),
+                    SugenoTerm::Linear(coefficients, constant) => coefficients.iter()
+                        .filter_map(|(c, k)| fuzzer.categories.get(c).map(|set| (set_range(set), k)))
+                        .fold((*constant, *constant), |(a, b), ((from, to), k)|
+                            (a+(k*from).min(k*to), b+(k*from).max(k*to)))
+                })
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), (from, to)| (a.min(from), b.max(to)));
+            // MATLAB needs min < max, a single value is widened.
+            let (from, to) = if from < to {
+                (from, to)
+            } else if from == to {
+                (from-1.0, to+1.0)
+            } else {
+                (0.0, 1.0)
+            };
+            writeln!(out, "Name='{}'\nRange=[{} {}]\nNumMFs={}", category, from, to, output_terms[i].len()).unwrap();
+            for (j, term) in output_terms[i].iter().enumerate() {
+                let (_, function) = set.terms().find(|(key, _)| *key == term).unwrap();
+                let (kind, params) = match function {
+                    SugenoTerm::Constant(value) => ("constant", vec![*value]),
+                    SugenoTerm::Linear(coefficients, constant) => {
+                        if let Some(category) = coefficients.keys().find(|c| !inputs.contains(c)) {
+                            Err(FuzzyError::InvalidCategory(category.clone()))?
+                        }
+                        let mut params = inputs.iter()
+                            .map(|c| coefficients.get(c).cloned().unwrap_or(0.0))
+                            .collect::<Vec<f64>>();
+                        params.push(*constant);
+                        ("linear", params)
+                    }
+                };
+                writeln!(out, "MF{}='{}':'{}',{}", j+1, term, kind, numbers(&params)).unwrap();
+            }
+        } else {
+            write_set(&mut out, category, &fuzzer.outputs[category], &output_terms[i])?;
+        }
+    }
+
+    writeln!(out, "\n[Rules]").unwrap();
+    let index = |names: &[Category], terms: &[Vec<Term>], (category, term): &FuzzyIdent| {
+        let i = names.iter().position(|c| c == category)
+            .ok_or(FuzzyError::InvalidCategory(category.clone()))?;
+        if !term.hedges.is_empty() {
+            Err(unsupported(format!("hedge in {}", term)))?
+        }
+        let j = terms[i].iter().position(|t| *t == term.term)
+            .ok_or(FuzzyError::InvalidTerm(term.term.clone()))?;
+        Ok::<_, FuzzyError>((i, j as i64+1))
+    };
+    for rule in fuzzer.rules.iter() {
+        let (items, connective) = match &rule.antecedent {
+            Antecedent::And(items) => (items.clone(), 1),
+            Antecedent::Or(items) => (items.clone(), 2),
+            other => (vec![other.clone()], 1)
+        };
+        let mut clauses = vec![0; inputs.len()];
+        for item in items.iter() {
+            let (ident, sign) = match item {
+                Antecedent::Is(ident) => (ident, 1),
+                Antecedent::Not(inner) => match inner.as_ref() {
+                    Antecedent::Is(ident) => (ident, -1),
+                    _ => Err(unsupported("negated expression".to_string()))?
+                },
+                _ => Err(unsupported("nested expression".to_string()))?
+            };
+            let (i, j) = index(&inputs, &input_terms, ident)?;
+            if clauses[i] != 0 {
+                Err(unsupported(format!("repeated input {} in rule", ident.0)))?
+            }
+            clauses[i] = sign*j;
+        }
+        let mut consequents = vec![0; outputs.len()];
+        let (i, j) = index(&outputs, &output_terms, &rule.consequent)?;
+        consequents[i] = j;
+        let join = |indices: Vec<i64>| indices.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(" ");
+        writeln!(out, "{}, {} ({}) : {}", join(clauses), join(consequents), rule.weight, connective).unwrap();
+    }
+    Ok(out)
+}
+
+/// Universe of the set, or span of its terms if unbounded.
+fn set_range(
+    set: &FuzzySet
+) -> (f64, f64) {
+    set.bounds().unwrap_or_else(|| set.terms()
+        .map(|(_, function)| function.range())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), (from, to)| (a.min(from), b.max(to))))
+}
+
+fn write_set(
+    out: &mut String,
+    category: &str,
+    set: &FuzzySet,
+    terms: &[Term]
+) -> FuzzyResult<()> {
+    if set.period.is_some() {
+        Err(FuzzyError::InvalidParameters(format!("periodic set {} has no .fis equivalent", category)))?
+    }
+    // Range is read back with the default policy.
+    if set.universe.is_some() && set.out_of_range != RangePolicy::Clamp {
+        Err(FuzzyError::InvalidParameters(format!("{:?} out of range of {} has no .fis equivalent", set.out_of_range, category)))?
+    }
+    let (from, to) = set_range(set);
+    writeln!(out, "Name='{}'\nRange=[{} {}]\nNumMFs={}", category, from, to, terms.len()).unwrap();
+    for (i, term) in terms.iter().enumerate() {
+        use MembershipFunction::*;
+        let (kind, params) = match set.function(term.clone())? {
+            Triangular { a, b, c } => ("trimf", vec![*a, *b, *c]),
+            Trapezoidal { a, b, c, d } => ("trapmf", vec![*a, *b, *c, *d]),
+            Gaussian { mean, sigma } => ("gaussmf", vec![*sigma, *mean]),
+            Bell { a, b, c } => ("gbellmf", vec![*a, *b, *c]),
+            Sigmoid { a, c } => ("sigmf", vec![*a, *c]),
+            S { a, b } => ("smf", vec![*a, *b]),
+            Z { a, b } => ("zmf", vec![*a, *b]),
+            Pi { a, b, c, d } => ("pimf", vec![*a, *b, *c, *d]),
+            Polyline(points) => {
+                let xs = points.iter().map(|(x, _)| *x).collect::<Vec<f64>>();
+                let ys = points.iter().map(|(_, y)| *y).collect::<Vec<f64>>();
+                match ys.as_slice() {
+                    [0.0, 1.0] => ("linsmf", xs),
+                    [1.0, 0.0] => ("linzmf", xs),
+                    [0.0, 1.0, 0.0] => ("trimf", xs),
+                    [0.0, 1.0, 1.0, 0.0] => ("trapmf", xs),
+                    [1.0, 1.0, 0.0] => ("trapmf", vec![xs[0], xs[0], xs[1], xs[2]]),
+                    [0.0, 1.0, 1.0] => ("trapmf", vec![xs[0], xs[1], xs[2], xs[2]]),
+                    _ => Err(FuzzyError::InvalidParameters(
+                        format!("polyline {} has no .fis equivalent", term)))?
+                }
+            },
+            Hedged(_, _) => Err(FuzzyError::InvalidParameters(
+                format!("hedged term {} has no .fis equivalent", term)))?
+        };
+        writeln!(out, "MF{}='{}':'{}',{}", i+1, term, kind, numbers(&params)).unwrap();
+    }
+    Ok(())
+}
+
+fn numbers(
+    values: &[f64]
+) -> String {
+    format!("[{}]", values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" "))
+}
+
+/// `[Name]` section with its `key=value` entries or, for rules, raw lines.
+struct Section {
+    name: String,
+    line: usize,
+    entries: HashMap<String, (String, usize, usize)>,
+    lines: Vec<(usize, String)>
+}
+
+fn sections(
+    source: &str
+) -> FuzzyResult<Vec<Section>> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (i, raw) in source.lines().enumerate() {
+        let line = i+1;
+        let text = raw.trim();
+        if text.is_empty() || text.starts_with('%') {
+            continue;
+        }
+        if let Some(name) = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) {
+            sections.push(Section {
+                name: name.to_string(),
+                line,
+                entries: HashMap::new(),
+                lines: Vec::new()
+            });
+            continue;
+        }
+        let section = sections.last_mut().ok_or(FuzzyError::Parse {
+            line,
+            column: 1,
+            message: "expected section header".to_string()
+        })?;
+        if section.name == "Rules" {
+            section.lines.push((line, text.to_string()));
+            continue;
+        }
+        let (key, value) = text.split_once('=').ok_or(FuzzyError::Parse {
+            line,
+            column: 1,
+            message: "expected key=value".to_string()
+        })?;
+        let column = raw.find('=').unwrap()+2;
+        section.entries.insert(key.trim().to_string(), (value.trim().to_string(), line, column));
+    }
+    Ok(sections)
+}
+
+impl Section {
+    /// Error at value of given key or at section header.
+    fn error(
+        &self,
+        key: &str,
+        message: &str
+    ) -> FuzzyError {
+        let (line, column) = self.entries.get(key)
+            .map(|(_, line, column)| (*line, *column))
+            .unwrap_or((self.line, 1));
+        FuzzyError::Parse { line, column, message: format!("[{}] {}: {}", self.name, key, message) }
+    }
+
+    fn value(
+        &self,
+        key: &str
+    ) -> FuzzyResult<&str> {
+        self.entries.get(key)
+            .map(|(value, _, _)| value.as_str())
+            .ok_or_else(|| self.error(key, "missing key"))
+    }
+
+    /// Value with quotes stripped.
+    fn text(
+        &self,
+        key: &str
+    ) -> FuzzyResult<String> {
+        Ok(self.value(key)?.trim_matches('\'').to_string())
+    }
+
+    fn number(
+        &self,
+        key: &str
+    ) -> FuzzyResult<f64> {
+        self.value(key)?.parse::<f64>().map_err(|_| self.error(key, "expected number"))
+    }
+
+    fn numbers(
+        &self,
+        key: &str,
+        text: &str
+    ) -> FuzzyResult<Vec<f64>> {
+        text.trim()
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+            .ok_or_else(|| self.error(key, "expected [...]"))?
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|number| !number.is_empty())
+            .map(|number| number.parse::<f64>().map_err(|_| self.error(key, "expected number")))
+            .collect()
+    }
+
+    /// `'name':'type',[params]`
+    fn mf(
+        &self,
+        key: &str
+    ) -> FuzzyResult<(Term, String, Vec<f64>)> {
+        let value = self.value(key)?;
+        let (name, rest) = value.split_once(':').ok_or_else(|| self.error(key, "expected ':'"))?;
+        let (kind, params) = rest.split_once(',').ok_or_else(|| self.error(key, "expected ','"))?;
+        Ok((
+            name.trim().trim_matches('\'').to_string(),
+            kind.trim().trim_matches('\'').to_string(),
+            self.numbers(key, params)?
+        ))
+    }
+
+    /// Bounds of input or Mamdani output.
+    fn range(
+        &self
+    ) -> FuzzyResult<(f64, f64)> {
+        let range = self.numbers("Range", self.value("Range")?)?;
+        if range.len() != 2 || range[0] >= range[1] {
+            Err(self.error("Range", "expected [min max] with min < max"))?
+        }
+        Ok((range[0], range[1]))
+    }
+
+    /// Empty set bounded by the range of input or Mamdani output.
+    fn universe(
+        &self
+    ) -> FuzzyResult<FuzzySet> {
+        let (from, to) = self.range()?;
+        FuzzySet::new().universe(from, to)
+    }
+
+    /// Name and terms of input or Mamdani output in file order.
+    fn terms(
+        &self
+    ) -> FuzzyResult<(Category, Vec<(Term, MembershipFunction)>)> {
+        let (from, to) = self.range()?;
+        // Functions without equivalent are sampled over the range.
+        let sample = |f: &dyn Fn(f64) -> f64| MembershipFunction::Polyline(
+            (0..SAMPLES)
+                .map(|i| from+(to-from)*i as f64/(SAMPLES-1) as f64)
+                .map(|x| (x, f(x)))
+                .collect()
+        );
+        let sigmoid = |a: f64, c: f64, x: f64| 1.0/(1.0+(-a*(x-c)).exp());
+        let gauss = |sigma: f64, mean: f64, x: f64| (-(x-mean).powi(2)/(2.0*sigma.powi(2))).exp();
+        let mut terms = Vec::new();
+        for i in 1..=self.number("NumMFs")? as usize {
+            let key = format!("MF{}", i);
+            let (term, kind, p) = self.mf(&key)?;
+            let expected = match kind.as_str() {
+                "linsmf" | "linzmf" | "sigmf" | "smf" | "zmf" => 2,
+                "trimf" | "gbellmf" => 3,
+                "trapmf" | "pimf" | "gauss2mf" | "dsigmf" | "psigmf" => 4,
+                "gaussmf" => 2,
+                _ => Err(self.error(&key, &format!("unsupported function '{}'", kind)))?
+            };
+            if p.len() != expected {
+                Err(self.error(&key, &format!("expected {} parameters", expected)))?
+            }
+            use MembershipFunction::*;
+            let function = match kind.as_str() {
+                "trimf" => Triangular { a: p[0], b: p[1], c: p[2] },
+                "trapmf" => Trapezoidal { a: p[0], b: p[1], c: p[2], d: p[3] },
+                "gaussmf" => Gaussian { mean: p[1], sigma: p[0] },
+                "gbellmf" => Bell { a: p[0], b: p[1], c: p[2] },
+                "sigmf" => Sigmoid { a: p[0], c: p[1] },
+                "smf" => S { a: p[0], b: p[1] },
+                "zmf" => Z { a: p[0], b: p[1] },
+                "pimf" => Pi { a: p[0], b: p[1], c: p[2], d: p[3] },
+                "linsmf" => Polyline(vec![(p[0], 0.0), (p[1], 1.0)]),
+                "linzmf" => Polyline(vec![(p[0], 1.0), (p[1], 0.0)]),
+                "gauss2mf" => sample(&|x| {
+                    let left = if x < p[1] { gauss(p[0], p[1], x) } else { 1.0 };
+                    let right = if x > p[3] { gauss(p[2], p[3], x) } else { 1.0 };
+                    left*right
+                }),
+                "dsigmf" => sample(&|x| (sigmoid(p[0], p[1], x)-sigmoid(p[2], p[3], x)).abs()),
+                _ => sample(&|x| sigmoid(p[0], p[1], x)*sigmoid(p[2], p[3], x))
+            };
+            terms.push((term, function.validate()?));
+        }
+        Ok((self.text("Name")?, terms))
+    }
+}
+
+#[test]
+fn test_fis(
+) -> FuzzyResult<()> {
+    use super::membership::MembershipFunction::*;
+    let source = "
+[System]
+Name='tipper'
+Type='mamdani'
+Version=2.0
+NumInputs=2
+NumOutputs=1
+NumRules=3
+AndMethod='min'
+OrMethod='max'
+ImpMethod='min'
+AggMethod='max'
+DefuzzMethod='centroid'
+
+[Input1]
+Name='service'
+Range=[0 10]
+NumMFs=3
+MF1='poor':'gaussmf',[1.5 0]
+MF2='good':'gaussmf',[1.5 5]
+MF3='excellent':'gauss2mf',[1.5 10 1 12]
+
+[Input2]
+Name='food'
+Range=[0 10]
+NumMFs=2
+MF1='rancid':'trapmf',[0 0 1 3]
+MF2='delicious':'trapmf',[7 9 10 10]
+
+[Output1]
+Name='tip'
+Range=[0 30]
+NumMFs=3
+MF1='cheap':'trimf',[0 5 10]
+MF2='average':'trimf',[10 15 20]
+MF3='generous':'trimf',[20 25 30]
+
+[Rules]
+1 1, 1 (1) : 2
+2 0, 2 (1) : 1
+3 2, 3 (0.5) : 1
+";
+    let fuzzer = from_fis(source)?;
+    let values = crate::values! { "service" => 5.0; "food" => 5.0 };
+    // poor(5) = 0.0039 clips cheap, which pulls centroid below 15.
+    let tip = fuzzer.evaluate(&values)?["tip"];
+    assert!(tip < 15.0 && tip > 14.8);
+    assert_eq!(fuzzer.rules[2], FuzzyRule::new(
+        Antecedent::And(vec![
+            Antecedent::Is(("service".to_string(), "excellent".into())),
+            Antecedent::Is(("food".to_string(), "delicious".into()))
+        ]),
+        ("tip".to_string(), "generous".into())
+    ).weight(0.5));
+
+    // Round trip, sampled gauss2mf is not exportable.
+    let fuzzer = fuzzer
+        .fuzzify("service", FuzzySet::new()
+            .term("poor", Z { a: 0.0, b: 4.0 })?
+            .term("good", Pi { a: 2.0, b: 4.0, c: 6.0, d: 8.0 })?
+            .term("excellent", vec![(6.0, 0.0), (9.0, 1.0), (10.0, 1.0)])?)
+        .rule(crate::unit!("food" => !"rancid"; "tip" => "average"));
+    let exported = to_fis(&fuzzer, "tipper")?;
+    let imported = from_fis(&exported)?;
+    assert_eq!(to_fis(&imported, "tipper")?, exported);
+    for (service, food) in [(1.0, 2.0), (5.0, 5.0), (8.0, 9.0), (9.5, 7.5)] {
+        let values = crate::values! { "service" => service; "food" => food };
+        assert_eq!(imported.evaluate(&values)?, fuzzer.evaluate(&values)?);
+    }
+
+    let sugeno = from_fis(&source
+        .replace("'mamdani'", "'sugeno'")
+        .replace("'centroid'", "'wtaver'")
+        .replace("MF1='cheap':'trimf',[0 5 10]", "MF1='cheap':'constant',[5]")
+        .replace("MF2='average':'trimf',[10 15 20]", "MF2='average':'linear',[1 0 10]")
+        .replace("MF3='generous':'trimf',[20 25 30]", "MF3='generous':'constant',[25]"))?;
+    let tip = sugeno.evaluate(&values)?["tip"];
+    let poor = (-25.0/4.5f64).exp();
+    assert!((tip-(poor*5.0+15.0)/(poor+1.0)).abs() < 1e-9);
+    // average = service+10 spans [10, 20] over the service range.
+    let sugeno = sugeno.fuzzify("service", fuzzer.categories["service"].clone());
+    assert!(to_fis(&sugeno, "tipper")?.contains("Name='tip'\nRange=[5 25]\n"));
+    let flat = Fuzzer::new().sugeno("tip", SugenoSet::new().constant("flat", 5.0));
+    assert!(to_fis(&flat, "flat")?.contains("Name='tip'\nRange=[4 6]\n"));
+
+    let invalid = source.replace("MF2='good':'gaussmf',[1.5 5]", "MF2='good':'gaussmf',[1.5]");
+    assert_eq!(from_fis(&invalid).err(), Some(FuzzyError::Parse {
+        line: 20,
+        column: 5,
+        message: "[Input1] MF2: expected 2 parameters".to_string()
+    }));
+
+    let periodic = Fuzzer::new().fuzzify("heading", FuzzySet::new()
+        .periodic(360.0)?
+        .term("north", Triangular { a: -30.0, b: 0.0, c: 30.0 })?);
+    assert_eq!(to_fis(&periodic, "compass").err(),
+               Some(FuzzyError::InvalidParameters("periodic set heading has no .fis equivalent".to_string())));
+    let strict = imported.fuzzify("food", FuzzySet::new()
+        .universe(0.0, 10.0)?
+        .out_of_range(RangePolicy::Error)
+        .term("rancid", Trapezoidal { a: 0.0, b: 0.0, c: 1.0, d: 3.0 })?
+        .term("delicious", Trapezoidal { a: 7.0, b: 9.0, c: 10.0, d: 10.0 })?);
+    assert_eq!(to_fis(&strict, "tipper").err(),
+               Some(FuzzyError::InvalidParameters("Error out of range of food has no .fis equivalent".to_string())));
+    Ok(())
+}
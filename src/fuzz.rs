@@ -1,5 +1,18 @@
 use std::collections::HashMap;
 use super::set::FuzzySet;
+use super::sugeno::{SugenoSet, SugenoTerm};
+use super::ops::{TNorm, SNorm, Implication, Aggregation, Complement};
+use super::membership::{combine, HedgedTerm};
+use super::defuzz::Defuzzifier;
+use super::parse::parse_rules;
+use super::explain::{
+    Explanation,
+    InputExplanation,
+    RuleExplanation,
+    OutputExplanation,
+    ConsequentExplanation,
+    Method
+};
 use super::common::{
     Category,
     Term,
@@ -9,19 +22,156 @@ use super::common::{
     FuzzyError
 };
 
-pub struct FuzzerConfig {}
+/// Name of the single term of sets returned by `Fuzzer::apply`.
+pub const OUTPUT_TERM: &str = "out";
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FuzzerConfig {
+    pub(crate) and: TNorm,
+    pub(crate) or: SNorm,
+    pub(crate) complement: Complement,
+    pub(crate) implication: Implication,
+    pub(crate) aggregation: Aggregation,
+    pub(crate) defuzzifier: Defuzzifier
+}
+
 impl FuzzerConfig {
     pub fn new(
     ) -> Self {
-        Self {}
+        Self {
+            and: TNorm::Min,
+            or: SNorm::Max,
+            complement: Complement::Standard,
+            implication: Implication::Min,
+            aggregation: Aggregation::Max,
+            defuzzifier: Defuzzifier::Centroid
+        }
+    }
+
+    /// Sets conjunction operator. MIN by default.
+    pub fn and(
+        mut self,
+        norm: TNorm
+    ) -> Self {
+        self.and = norm;
+        self
+    }
+
+    /// Sets disjunction operator. MAX by default.
+    pub fn or(
+        mut self,
+        norm: SNorm
+    ) -> Self {
+        self.or = norm;
+        self
+    }
+
+    /// Sets negation operator. 1-x by default.
+    pub fn complement(
+        mut self,
+        complement: Complement
+    ) -> Self {
+        self.complement = complement;
+        self
+    }
+
+    /// Sets implication method. MIN (clipping) by default.
+    pub fn implication(
+        mut self,
+        implication: Implication
+    ) -> Self {
+        self.implication = implication;
+        self
+    }
+
+    /// Sets aggregation method of rule consequents. MAX by default.
+    /// It is applied to consequents of all rules, regardless whether they hit
+    /// the same or different term of the output.
+    pub fn aggregation(
+        mut self,
+        aggregation: Aggregation
+    ) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Sets default defuzzification method used by `Fuzzer::evaluate`. COG by default.
+    pub fn defuzzifier(
+        mut self,
+        defuzzifier: Defuzzifier
+    ) -> Self {
+        self.defuzzifier = defuzzifier;
+        self
+    }
+
+    /// Checks parameters of the operators.
+    pub fn validate(
+        &self
+    ) -> FuzzyResult<()> {
+        self.and.validate()?;
+        self.or.validate()?;
+        self.complement.validate()?;
+        if let Implication::Norm(norm) = &self.implication {
+            norm.validate()?;
+        }
+        Ok(())
     }
 }
 
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Fuzzer {
-    categories: HashMap<Category, FuzzySet>,
-    outputs: HashMap<Category, FuzzySet>,
-    rules: Vec<FuzzyRule>,
-    _config: FuzzerConfig
+    #[cfg_attr(feature = "serde", serde(rename = "inputs"))]
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+    pub(crate) categories: HashMap<Category, FuzzySet>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+    pub(crate) outputs: HashMap<Category, FuzzySet>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+    pub(crate) defuzzifiers: HashMap<Category, Defuzzifier>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+    pub(crate) sugeno: HashMap<Category, SugenoSet>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+    pub(crate) tsukamoto: HashMap<Category, FuzzySet>,
+    pub(crate) rules: Vec<FuzzyRule>,
+    pub(crate) config: FuzzerConfig
+}
+
+/// Loaded Fuzzer is checked like one built by hand: Tsukamoto outputs have to be
+/// monotonic and `Fuzzer::validate` has to pass.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fuzzer {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<Self, D::Error> {
+        #[derive(Default, serde::Deserialize)]
+        #[serde(default)]
+        struct Repr {
+            inputs: HashMap<Category, FuzzySet>,
+            outputs: HashMap<Category, FuzzySet>,
+            defuzzifiers: HashMap<Category, Defuzzifier>,
+            sugeno: HashMap<Category, SugenoSet>,
+            tsukamoto: HashMap<Category, FuzzySet>,
+            rules: Vec<FuzzyRule>,
+            config: FuzzerConfig
+        }
+        let repr = Repr::deserialize(deserializer)?;
+        let fuzzer = Fuzzer {
+            categories: repr.inputs,
+            outputs: repr.outputs,
+            defuzzifiers: repr.defuzzifiers,
+            sugeno: repr.sugeno,
+            tsukamoto: HashMap::new(),
+            rules: repr.rules,
+            config: repr.config
+        };
+        let fuzzer = repr.tsukamoto.into_iter()
+            .try_fold(fuzzer, |fuzzer, (category, set)| fuzzer.tsukamoto(category, set))
+            .map_err(serde::de::Error::custom)?;
+        fuzzer.validate().map_err(serde::de::Error::custom)?;
+        Ok(fuzzer)
+    }
 }
 
 impl Fuzzer {
@@ -30,32 +180,310 @@ impl Fuzzer {
         Self {
             categories: HashMap::new(),
             outputs: HashMap::new(),
+            defuzzifiers: HashMap::new(),
+            sugeno: HashMap::new(),
+            tsukamoto: HashMap::new(),
             rules: Vec::new(),
-            _config: FuzzerConfig::new()
+            config: FuzzerConfig::new()
         }
     }
 
+    /// Replaces configuration of the Fuzzer.
+    pub fn config(
+        mut self,
+        config: FuzzerConfig
+    ) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Applies rules to input and returns calculated FuzzySets.
+    /// Each set consists of a single term named `OUTPUT_TERM`.
+    /// Sugeno and Tsukamoto outputs are skipped, as they have no output set.
     pub fn apply(
         &self,
         values: &HashMap<Category, f64>
     ) -> FuzzyResult<HashMap<Category, FuzzySet>> {
+        let _span = tracing::debug_span!("apply", inputs = ?values).entered();
+        self.builders(&self.fire(values)?)?
+            .into_iter()
+            .map(|(k, v)| {
+                let _span = tracing::debug_span!("output", category = %k).entered();
+                v.build(OUTPUT_TERM).map(|set| (k, set))
+            })
+            .collect()
+    }
+
+    /// Applies rules to input and returns crisp value of every output.
+    /// Fails with `NoRuleFired` if none of the rules fired for some output.
+    pub fn evaluate(
+        &self,
+        values: &HashMap<Category, f64>
+    ) -> FuzzyResult<HashMap<Category, f64>> {
+        let _span = tracing::debug_span!("evaluate", inputs = ?values).entered();
+        let fired = self.fire(values)?;
+        let mut builders = self.builders(&fired)?;
+        let mamdani = self.outputs.keys()
+            .map(|category| {
+                let builder = builders.remove(category)
+                    .filter(|builder| builder.fired())
+                    .ok_or_else(|| FuzzyError::NoRuleFired(category.clone()))?;
+                let defuzzifier = self.defuzzifiers.get(category)
+                    .unwrap_or(&self.config.defuzzifier);
+                let _span = tracing::debug_span!("defuzzify", category = %category, method = ?defuzzifier).entered();
+                let y = builder.defuzzify(defuzzifier)
+                    .map_err(|error| error.in_category(category.clone()))?;
+                tracing::debug!(value = y, "defuzzified");
+                Ok((category.clone(), y))
+            });
+        // Weighted average of rule outputs. No output set is built.
+        let sugeno = self.sugeno.iter()
+            .map(|(category, set)| {
+                let _span = tracing::debug_span!("defuzzify", category = %category, method = "sugeno").entered();
+                let y = weighted_average(category, &Self::sugeno_consequents(category, set, &fired, values)?)?;
+                tracing::debug!(value = y, "defuzzified");
+                Ok((category.clone(), y))
+            });
+        // Weighted average of firing strengths inverted through consequent terms.
+        let tsukamoto = self.tsukamoto.iter()
+            .map(|(category, set)| {
+                let _span = tracing::debug_span!("defuzzify", category = %category, method = "tsukamoto").entered();
+                let y = weighted_average(category, &Self::tsukamoto_consequents(category, set, &fired)?)?;
+                tracing::debug!(value = y, "defuzzified");
+                Ok((category.clone(), y))
+            });
+        mamdani.chain(sugeno).chain(tsukamoto).collect()
+    }
+
+    /// Applies rules to input like `evaluate`, recording every intermediate result.
+    /// Outputs for which no rule fired are reported without value instead of failing.
+    pub fn explain(
+        &self,
+        values: &HashMap<Category, f64>
+    ) -> FuzzyResult<Explanation> {
+        let _span = tracing::debug_span!("explain", inputs = ?values).entered();
+        let mut inputs = Vec::new();
+        for (category, set) in self.categories.iter() {
+            if let Some(x) = values.get(category) {
+                let mut memberships = set.terms()
+                    .map(|(term, _)| Ok((term.clone(), set.call_single(term.clone(), *x)?)))
+                    .collect::<FuzzyResult<Vec<_>>>()?;
+                memberships.sort_by(|(a, _), (b, _)| a.cmp(b));
+                inputs.push(InputExplanation { category: category.clone(), value: *x, memberships });
+            }
+        }
+        inputs.sort_by(|a, b| a.category.cmp(&b.category));
+
+        let fired = self.fire(values)?;
+        let rules = self.rules.iter()
+            .zip(fired.iter())
+            .enumerate()
+            .map(|(index, (rule, (_, _, strength)))| RuleExplanation {
+                index,
+                rule: rule.to_string(),
+                strength: *strength
+            })
+            .collect();
+
+        let mut outputs = Vec::new();
+        let mut builders = self.builders(&fired)?;
+        for category in self.outputs.keys() {
+            let defuzzifier = self.defuzzifiers.get(category)
+                .unwrap_or(&self.config.defuzzifier);
+            let explain = |builder: &FuzzySetBuilder| -> FuzzyResult<OutputExplanation> {
+                let shaped = builder.consequents()?;
+                let consequents = builder.values.iter()
+                    .zip(shaped.iter())
+                    .map(|((term, strength), points)| ConsequentExplanation {
+                        term: term.to_string(),
+                        strength: *strength,
+                        points: points.clone(),
+                        value: None
+                    })
+                    .collect();
+                let aggregated = builder.build(OUTPUT_TERM)?.points(OUTPUT_TERM)?;
+                let value = if builder.fired() {
+                    Some(builder.reduce(defuzzifier, &aggregated, &shaped)?)
+                } else {
+                    None
+                };
+                Ok(OutputExplanation {
+                    category: category.clone(),
+                    method: Method::Mamdani(defuzzifier.clone()),
+                    consequents,
+                    aggregated,
+                    value
+                })
+            };
+            let output = match builders.remove(category) {
+                Some(builder) => explain(&builder).map_err(|error| error.in_category(category.clone()))?,
+                None => OutputExplanation {
+                    category: category.clone(),
+                    method: Method::Mamdani(defuzzifier.clone()),
+                    consequents: Vec::new(),
+                    aggregated: Vec::new(),
+                    value: None
+                }
+            };
+            outputs.push(output);
+        }
+        let crisp = |category: &Category, method: Method, consequents: Vec<(HedgedTerm, f64, f64)>| OutputExplanation {
+            category: category.clone(),
+            method,
+            value: weighted_average(category, &consequents).ok(),
+            consequents: consequents.into_iter()
+                .map(|(term, strength, y)| ConsequentExplanation {
+                    term: term.to_string(),
+                    strength,
+                    points: Vec::new(),
+                    value: Some(y)
+                })
+                .collect(),
+            aggregated: Vec::new()
+        };
+        for (category, set) in self.sugeno.iter() {
+            outputs.push(crisp(category, Method::Sugeno, Self::sugeno_consequents(category, set, &fired, values)?));
+        }
+        for (category, set) in self.tsukamoto.iter() {
+            outputs.push(crisp(category, Method::Tsukamoto, Self::tsukamoto_consequents(category, set, &fired)?));
+        }
+        outputs.sort_by(|a, b| a.category.cmp(&b.category));
+
+        Ok(Explanation { inputs, rules, outputs })
+    }
+
+    /// Terms, firing strengths and crisp values of rules concluding Sugeno output.
+    fn sugeno_consequents(
+        category: &Category,
+        set: &SugenoSet,
+        fired: &[FuzzyValue],
+        values: &HashMap<Category, f64>
+    ) -> FuzzyResult<Vec<(HedgedTerm, f64, f64)>> {
+        let mut consequents = Vec::new();
+        for (_, term, w) in fired.iter().filter(|(c, _, _)| c == category) {
+            if !term.hedges.is_empty() {
+                Err(FuzzyError::InvalidTerm(term.to_string()).in_category(category.clone()))?
+            }
+            let y = set.call_single(term.term.clone(), values)
+                .map_err(|error| error.in_category(category.clone()))?;
+            consequents.push((term.clone(), *w, y));
+        }
+        Ok(consequents)
+    }
+
+    /// Terms, firing strengths and inverted values of fired rules concluding Tsukamoto output.
+    fn tsukamoto_consequents(
+        category: &Category,
+        set: &FuzzySet,
+        fired: &[FuzzyValue]
+    ) -> FuzzyResult<Vec<(HedgedTerm, f64, f64)>> {
+        let mut consequents = Vec::new();
+        for (_, term, w) in fired.iter().filter(|(c, _, _)| c == category) {
+            if *w > 0.0 {
+                let key = term.to_string();
+                let y = set.resolve(term)
+                    .and_then(|function| set.empty_like().term(key.clone(), function))
+                    .and_then(|hedged| hedged.invert(key, *w))
+                    .map_err(|error| error.in_category(category.clone()))?;
+                consequents.push((term.clone(), *w, y));
+            }
+        }
+        Ok(consequents)
+    }
+
+    /// Checks configuration and every rule against registered inputs and outputs,
+    /// before the Fuzzer is used. Invalid operator parameters fail with `InvalidParameters`.
+    /// Otherwise all unknown categories and terms, including inputs of linear Sugeno
+    /// consequents, invalid weights, empty AND/OR and hedged Sugeno consequents are
+    /// reported at once as `InvalidRules`.
+    pub fn validate(
+        &self
+    ) -> FuzzyResult<()> {
+        self.config.validate()?;
+        let mut issues = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            for connective in rule.antecedent.empty_connectives() {
+                issues.push((i, FuzzyError::InvalidParameters(format!("{} without operands", connective))));
+            }
+            for (category, term) in rule.antecedent.clauses() {
+                match self.categories.get(category) {
+                    None => issues.push((i, FuzzyError::InvalidCategory(category.clone()))),
+                    Some(set) => if set.function(term.term.clone()).is_err() {
+                        issues.push((i, FuzzyError::InvalidTerm(term.term.clone())));
+                    }
+                }
+            }
+            let (category, term) = &rule.consequent;
+            let known = if let Some(set) = self.outputs.get(category).or_else(|| self.tsukamoto.get(category)) {
+                set.function(term.term.clone()).is_ok()
+            } else if let Some(set) = self.sugeno.get(category) {
+                if !term.hedges.is_empty() {
+                    issues.push((i, FuzzyError::InvalidTerm(term.to_string())));
+                }
+                // Linear terms read inputs, which have to be registered.
+                let function = set.terms().find(|(key, _)| **key == term.term).map(|(_, function)| function);
+                if let Some(SugenoTerm::Linear(coefficients, _)) = function {
+                    let mut unknown = coefficients.keys()
+                        .filter(|input| !self.categories.contains_key(*input))
+                        .collect::<Vec<_>>();
+                    unknown.sort();
+                    issues.extend(unknown.into_iter().map(|input| (i, FuzzyError::InvalidCategory(input.clone()))));
+                }
+                function.is_some()
+            } else {
+                issues.push((i, FuzzyError::InvalidCategory(category.clone())));
+                true
+            };
+            if !known {
+                issues.push((i, FuzzyError::InvalidTerm(term.term.clone())));
+            }
+            if !(0.0..=1.0).contains(&rule.weight) {
+                issues.push((i, FuzzyError::InvalidParameters(format!("rule weight {} out of [0, 1]", rule.weight))));
+            }
+        }
+        if !issues.is_empty() {
+            Err(FuzzyError::InvalidRules(issues))?
+        }
+        Ok(())
+    }
+
+    /// Fires all rules.
+    fn fire(
+        &self,
+        values: &HashMap<Category, f64>
+    ) -> FuzzyResult<Vec<FuzzyValue>> {
+        let _span = tracing::debug_span!("fire", rules = self.rules.len()).entered();
+        self.rules.iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let _span = tracing::debug_span!("rule", index = i).entered();
+                let fired = rule.apply(self, values).map_err(|error| error.in_rule(i))?;
+                tracing::debug!(category = %fired.0, term = %fired.1, strength = fired.2, "fired");
+                Ok(fired)
+            })
+            .collect()
+    }
+
+    /// Groups fired rules by output category.
+    fn builders(
+        &self,
+        fired: &[FuzzyValue]
+    ) -> FuzzyResult<HashMap<Category, FuzzySetBuilder<'_>>> {
         let mut results: HashMap<Category, FuzzySetBuilder> = HashMap::new();
-        for rule in self.rules.iter() {
-            let (out_category, out_term, y) = rule.apply(&self, values)?;
+        for (out_category, out_term, y) in fired.iter().cloned() {
+            if self.sugeno.contains_key(&out_category) || self.tsukamoto.contains_key(&out_category) {
+                continue;
+            }
             // Get builder of outputs set or create new one using base set.
             let mut builder = results.remove(&out_category)
-                .or(self.outputs.get(&out_category).map(|base_set| FuzzySetBuilder::new(base_set)))
+                .or(self.outputs.get(&out_category).map(|base_set| FuzzySetBuilder::new(base_set, &self.config)))
                 .ok_or(FuzzyError::InvalidCategory(out_category.clone()))?;
-            // Apply threshold to givent term.
-            builder.threshold(&out_term, y);
+            // Register firing strength of given term.
+            builder.activate(out_term, y);
             // Put it bac in the Map.
             results.insert(out_category, builder);
         }
-        println!("RES: {:?}", results);
-        results.into_iter()
-            .map(|(k, v)| v.build("out").map(|set| (k, set)))
-            .collect()
+        Ok(results)
     }
 
     /// Adds new rule to the Fuzzer.
@@ -67,6 +495,15 @@ impl Fuzzer {
         self
     }
 
+    /// Adds rules written in text form, see `parse::parse_rules`.
+    pub fn rules_from_str(
+        mut self,
+        source: &str
+    ) -> FuzzyResult<Self> {
+        self.rules.extend(parse_rules(source)?);
+        Ok(self)
+    }
+
     /// Adds new input set to the Fuzzer.
     pub fn fuzzify(
         mut self,
@@ -87,122 +524,343 @@ impl Fuzzer {
         self
     }
 
+    /// Adds new Takagi-Sugeno-Kang output.
+    /// Its crisp value is the firing strength weighted average of rule consequents.
+    pub fn sugeno(
+        mut self,
+        ident: impl Into<Category>,
+        output: SugenoSet
+    ) -> Self {
+        self.sugeno.insert(ident.into(), output);
+        self
+    }
+
+    /// Adds new Tsukamoto output. Every term of the set has to be monotonic.
+    /// Its crisp value is the firing strength weighted average of strengths inverted through terms.
+    pub fn tsukamoto(
+        mut self,
+        ident: impl Into<Category>,
+        output: FuzzySet
+    ) -> FuzzyResult<Self> {
+        let ident = ident.into();
+        for (term, _) in output.terms() {
+            if !output.is_monotonic(term.clone())? {
+                Err(FuzzyError::NonMonotonicTerm(term.clone()).in_category(ident.clone()))?
+            }
+        }
+        self.tsukamoto.insert(ident, output);
+        Ok(self)
+    }
+
+    /// Adds new output set, which is defuzzified with given method instead of default one.
+    pub fn defuzzify_with(
+        mut self,
+        ident: impl Into<Category>,
+        output: FuzzySet,
+        defuzzifier: Defuzzifier
+    ) -> Self {
+        let ident = ident.into();
+        self.defuzzifiers.insert(ident.clone(), defuzzifier);
+        self.defuzzify(ident, output)
+    }
+
     fn call(
         &self,
         point: &FuzzyValue
     ) -> FuzzyResult<f64> {
         let (category, term, x) = point;
-        self.categories.get(category)
+        let _span = tracing::trace_span!("fuzzify", category = %category, term = %term, x).entered();
+        let y = self.categories.get(category)
             .ok_or(FuzzyError::InvalidCategory(category.clone()))?
-            .call(*x)?
-            .remove(term)
-            .ok_or(FuzzyError::InvalidTerm(term.clone()))
+            .call_hedged(term, *x)?;
+        tracing::trace!(membership = y);
+        Ok(y)
     }
 }
 
 #[derive(Debug)]
 struct FuzzySetBuilder<'a> {
     base: &'a FuzzySet,
-    values: HashMap<Term, f64>
+    config: &'a FuzzerConfig,
+    values: Vec<(HedgedTerm, f64)>
 }
 
 impl<'a> FuzzySetBuilder<'a> {
     fn new(
-        base: &'a FuzzySet
+        base: &'a FuzzySet,
+        config: &'a FuzzerConfig
     ) -> Self {
         Self {
             base,
-            values: HashMap::new()
+            config,
+            values: Vec::new()
         }
     }
 
-    fn threshold(
+    /// Stores firing strength of a rule with given consequent term.
+    fn activate(
         &mut self,
-        term: impl Into<Term>,
+        term: impl Into<HedgedTerm>,
         y: f64
     ) -> &mut Self {
-        let key = term.into();
-        // Accumulation method should be configurable.
-        // By default it takes MAX.
-        let accum = |old: f64, new: f64| if new > old { new } else { old };
-        let next = self.values.get(&key).map(|current| accum(*current, y)).unwrap_or_else(|| y);
-        self.values.insert(key, next);
+        self.values.push((term.into(), y));
         self
     }
 
+    /// Checks if any rule fired with non-zero strength.
+    fn fired(
+        &self
+    ) -> bool {
+        self.values.iter().any(|(_, y)| *y > 0.0)
+    }
+
+    /// Consequent term shaped with firing strength using implication method.
+    fn consequent(
+        &self,
+        term: &HedgedTerm,
+        y: f64
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        let key = term.to_string();
+        let mut set = self.base.empty_like()
+            .term(key.clone(), self.base.resolve(term)?)?;
+        set.apply_implication(key.clone(), y, &self.config.implication)?;
+        set.points(key)
+    }
+
     fn build(
         &self,
         term_name: impl Into<Term>
     ) -> FuzzyResult<FuzzySet> {
-        let mut set = self.base.clone();
-
-        println!("SET1: {:?}", self);
-        // Apply thresholds stored in self.values to output set
-        for (term, _) in self.base.terms() {
-            let thres = self.values.get(term).unwrap_or_else(|| &0.0);
-            println!("{}: {}", term, thres);
-            println!("BEFORE: {:?}", set);
-            set.apply_threshold(term, *thres)?;
-            println!("AFTER: {:?}", set);
-        }
-
-        // Calculate output set by evaluating xs of base output set.
+        let aggregation = &self.config.aggregation;
+        let _span = tracing::debug_span!("aggregate", method = ?aggregation, fired = self.values.len()).entered();
+        // Start with empty curve spanning whole base output set.
         let mut xs = self.base.terms()
-            .map(|(_, points)| points.iter().map(|(x, _)| *x).collect::<Vec<f64>>())
-            .flatten()
+            .flat_map(|(_, function)| self.base.curve(function).into_iter().map(|(x, _)| x))
             .collect::<Vec<f64>>();
-
-        xs.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        xs.sort_by(|x, y| x.total_cmp(y));
         xs.dedup();
+        let mut points = xs.into_iter().map(|x| (x, 0.0)).collect::<Vec<(f64, f64)>>();
+
+        // Aggregate consequents of all fired rules.
+        for (term, y) in self.values.iter() {
+            let consequent = self.consequent(term, *y)?;
+            tracing::trace!(term = %term, strength = y, points = consequent.len(), "aggregating consequent");
+            points = combine(&points, &consequent, |a, b| aggregation.call(a, b));
+        }
+
+        if let Aggregation::NormalizedSum = aggregation {
+            let height = points.iter().fold(0.0, |acc: f64, (_, y)| acc.max(*y));
+            if height > 1.0 {
+                points.iter_mut().for_each(|(_, y)| *y /= height);
+            }
+        }
+
+        self.base.empty_like().term(term_name.into(), points)
+    }
+
+    /// Consequents of all fired rules, in order of activation.
+    fn consequents(
+        &self
+    ) -> FuzzyResult<Vec<Vec<(f64, f64)>>> {
+        self.values.iter()
+            .map(|(term, y)| self.consequent(term, *y))
+            .collect()
+    }
+
+    /// Builds output set and reduces it to a crisp value.
+    fn defuzzify(
+        &self,
+        defuzzifier: &Defuzzifier
+    ) -> FuzzyResult<f64> {
+        let consequents = self.consequents()?;
+        let aggregated = self.build(OUTPUT_TERM)?.points(OUTPUT_TERM)?;
+        self.reduce(defuzzifier, &aggregated, &consequents)
+    }
 
-        let mut points = Vec::with_capacity(xs.len());
-        for x in xs.iter() {
-            let results = set.call(*x)?;
-            let y = results.into_iter().fold(0.0, |acc, (_, y)| acc+y);
-            points.push((*x, y));
+    /// Reduces output set to a crisp value, wrapping around periodic universes.
+    fn reduce(
+        &self,
+        defuzzifier: &Defuzzifier,
+        aggregated: &[(f64, f64)],
+        consequents: &[Vec<(f64, f64)>]
+    ) -> FuzzyResult<f64> {
+        match self.base.period {
+            Some(period) => defuzzifier.defuzzify_periodic(aggregated, consequents, period),
+            None => defuzzifier.defuzzify(aggregated, consequents)
         }
+    }
+}
 
-        FuzzySet::new().term(term_name.into(), points)
+/// Average of consequent values weighted with firing strengths.
+fn weighted_average(
+    category: &Category,
+    consequents: &[(HedgedTerm, f64, f64)]
+) -> FuzzyResult<f64> {
+    let (a, b) = consequents.iter()
+        .fold((0.0, 0.0), |(a, b), (_, w, y)| (a+w*y, b+w));
+    if b == 0.0 {
+        Err(FuzzyError::NoRuleFired(category.clone()))?
     }
+    Ok(a/b)
 }
 
-#[derive(Debug, PartialEq)]
-pub enum FuzzyRule {
-    Unit(FuzzyIdent, FuzzyIdent),
-    And(Vec<FuzzyIdent>, FuzzyIdent),
-    Or(Vec<FuzzyIdent>, FuzzyIdent)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FuzzyRule {
+    pub(crate) antecedent: Antecedent,
+    pub(crate) consequent: FuzzyIdent,
+    #[cfg_attr(feature = "serde", serde(default = "default_weight"))]
+    pub(crate) weight: f64
 }
 
-impl FuzzyRule {
+#[cfg(feature = "serde")]
+fn default_weight(
+) -> f64 {
+    1.0
+}
+
+/// Premise of a rule. Expression tree evaluated recursively.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Antecedent {
+    /// Category IS term.
+    Is(FuzzyIdent),
+    /// Negation using configured complement.
+    Not(Box<Antecedent>),
+    /// Conjunction using configured t-norm.
+    And(Vec<Antecedent>),
+    /// Disjunction using configured s-norm.
+    Or(Vec<Antecedent>)
+}
+
+impl Antecedent {
+    /// Replaces conjunctions and disjunctions of a single item with the item.
+    fn simplify(
+        self
+    ) -> Self {
+        match self {
+            Antecedent::Is(_) => self,
+            Antecedent::Not(inner) => !inner.simplify(),
+            Antecedent::And(mut items) | Antecedent::Or(mut items) if items.len() == 1 =>
+                items.pop().unwrap().simplify(),
+            Antecedent::And(items) => Antecedent::And(items.into_iter().map(Self::simplify).collect()),
+            Antecedent::Or(items) => Antecedent::Or(items.into_iter().map(Self::simplify).collect())
+        }
+    }
+
+    /// Conjunctions and disjunctions without items, which have no textual form.
+    fn empty_connectives(
+        &self
+    ) -> Vec<&'static str> {
+        match self {
+            Antecedent::Is(_) => Vec::new(),
+            Antecedent::Not(inner) => inner.empty_connectives(),
+            Antecedent::And(items) if items.is_empty() => vec!["and"],
+            Antecedent::Or(items) if items.is_empty() => vec!["or"],
+            Antecedent::And(items) | Antecedent::Or(items) => items.iter()
+                .flat_map(|item| item.empty_connectives())
+                .collect()
+        }
+    }
+
+    /// All `category IS term` clauses of the premise.
+    pub fn clauses(
+        &self
+    ) -> Vec<&FuzzyIdent> {
+        match self {
+            Antecedent::Is(ident) => vec![ident],
+            Antecedent::Not(inner) => inner.clauses(),
+            Antecedent::And(antecedents) | Antecedent::Or(antecedents) => antecedents.iter()
+                .flat_map(|antecedent| antecedent.clauses())
+                .collect()
+        }
+    }
+
     pub fn apply(
         &self,
         fuzzer: &Fuzzer,
         values: &HashMap<Category, f64>
-    ) -> FuzzyResult<FuzzyValue> {
-        // Extract fuzzy sets identifiers and output category
-        let (idents, (out_category, out_term)) = match self {
-            FuzzyRule::Unit(ident, out) => (vec![ident.clone()], out.clone()),
-            FuzzyRule::And(idents, out) => (idents.clone(), out.clone()),
-            FuzzyRule::Or(idents, out) => (idents.clone(), out.clone())
-        };
+    ) -> FuzzyResult<f64> {
+        let config = &fuzzer.config;
+        let apply_all = |antecedents: &Vec<Antecedent>| antecedents.iter()
+            .map(|antecedent| antecedent.apply(fuzzer, values))
+            .collect::<FuzzyResult<Vec<f64>>>();
+        match self {
+            Antecedent::Is((cat, term)) => values.get(cat)
+                .ok_or_else(|| FuzzyError::InvalidCategory(cat.clone()))
+                .and_then(|x| fuzzer.call(&(cat.clone(), term.clone(), *x))),
+            Antecedent::Not(inner) => inner.apply(fuzzer, values)
+                .map(|y| config.complement.call(y)),
+            // Empty conjunction is always true, empty disjunction always false.
+            Antecedent::And(antecedents) => Ok(apply_all(antecedents)?
+                .into_iter()
+                .reduce(|a, b| config.and.call(a, b))
+                .unwrap_or(1.0)),
+            Antecedent::Or(antecedents) => Ok(apply_all(antecedents)?
+                .into_iter()
+                .reduce(|a, b| config.or.call(a, b))
+                .unwrap_or(0.0))
+        }
+    }
+}
 
-        // Sample fuzzy sets
-        let mut ys = Vec::new();
-        for (cat, term) in idents {
-            let y = values.get(&cat)
-                .ok_or(FuzzyError::InvalidCategory(cat.clone()))
-                .and_then(|x| fuzzer.call(&(cat, term.clone(), *x)))?;
-            ys.push(y);
-        };
+impl std::ops::Not for Antecedent {
+    type Output = Antecedent;
+
+    fn not(
+        self
+    ) -> Self::Output {
+        Antecedent::Not(Box::new(self))
+    }
+}
+
+impl FuzzyRule {
+    /// Conjunctions and disjunctions of a single item are replaced with the item,
+    /// as the parser does.
+    pub fn new(
+        antecedent: Antecedent,
+        consequent: FuzzyIdent
+    ) -> Self {
+        Self {
+            antecedent: antecedent.simplify(),
+            consequent,
+            weight: 1.0
+        }
+    }
 
-        // Pick return value based on samples.
-        let cmp = |x: &f64, y: &f64| x.partial_cmp(y).unwrap();
-        let mut ys = ys.into_iter();
-        let y = match self {
-            FuzzyRule::Unit(_, _) => ys.next(),
-            FuzzyRule::And(_, _) => ys.min_by(cmp),
-            FuzzyRule::Or(_, _) => ys.max_by(cmp),
-        }.unwrap();
+    /// Sets certainty factor of the rule. It has to be in [0, 1], 1 by default.
+    pub fn weight(
+        mut self,
+        weight: f64
+    ) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn antecedent(
+        &self
+    ) -> &Antecedent {
+        &self.antecedent
+    }
+
+    pub fn consequent(
+        &self
+    ) -> &FuzzyIdent {
+        &self.consequent
+    }
+
+    /// Evaluates premise and returns consequent with firing strength multiplied by weight.
+    pub fn apply(
+        &self,
+        fuzzer: &Fuzzer,
+        values: &HashMap<Category, f64>
+    ) -> FuzzyResult<FuzzyValue> {
+        if !(0.0..=1.0).contains(&self.weight) {
+            Err(FuzzyError::InvalidParameters(format!("rule weight {} out of [0, 1]", self.weight)))?
+        }
+        let y = self.antecedent.apply(fuzzer, values)?*self.weight;
+        let (out_category, out_term) = self.consequent.clone();
         Ok((out_category, out_term, y))
     }
 }
@@ -210,68 +868,604 @@ impl FuzzyRule {
 
 #[macro_export]
 macro_rules! unit {
+    ($c1:expr => ! $t1:expr; $co:expr=>$to:expr; $w:expr) => {
+        $crate::unit!($c1 => !$t1; $co => $to).weight($w)
+    };
+    ($c1:expr=>$t1:expr; $co:expr=>$to:expr; $w:expr) => {
+        $crate::unit!($c1 => $t1; $co => $to).weight($w)
+    };
+    ($c1:expr => ! $t1:expr; $co:expr=>$to:expr) => {
+        $crate::FuzzyRule::new(
+            !$crate::Antecedent::Is((String::from($c1), $crate::HedgedTerm::from($t1))),
+            (String::from($co), $crate::HedgedTerm::from($to)))
+    };
     ($c1:expr=>$t1:expr; $co:expr=>$to:expr) => {
-        $crate::FuzzyRule::Unit(
-            (String::from($c1), String::from($t1)),
-            (String::from($co), String::from($to)))
+        $crate::FuzzyRule::new(
+            $crate::Antecedent::Is((String::from($c1), $crate::HedgedTerm::from($t1))),
+            (String::from($co), $crate::HedgedTerm::from($to)))
     }
 }
 
+// Munches comma separated antecedents of and!/or!/all!/any! macros one by one.
+// Every item is either a clause (`category => term`, `category => !term`)
+// or an Antecedent expression, e.g. nested any!(...).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rule {
+    ($typ:ident [$($acc:expr),*] $c:expr => ! $t:expr, $($rest:tt)*) => {
+        $crate::__rule!($typ [$($acc,)* !$crate::Antecedent::Is((String::from($c), $crate::HedgedTerm::from($t)))] $($rest)*)
+    };
+    ($typ:ident [$($acc:expr),*] $c:expr => ! $t:expr; $($rest:tt)*) => {
+        $crate::__rule!($typ [$($acc,)* !$crate::Antecedent::Is((String::from($c), $crate::HedgedTerm::from($t)))]; $($rest)*)
+    };
+    ($typ:ident [$($acc:expr),*] $c:expr => ! $t:expr) => {
+        $crate::__rule!($typ [$($acc,)* !$crate::Antecedent::Is((String::from($c), $crate::HedgedTerm::from($t)))])
+    };
+    ($typ:ident [$($acc:expr),*] $c:expr => $t:expr, $($rest:tt)*) => {
+        $crate::__rule!($typ [$($acc,)* $crate::Antecedent::Is((String::from($c), $crate::HedgedTerm::from($t)))] $($rest)*)
+    };
+    ($typ:ident [$($acc:expr),*] $c:expr => $t:expr; $($rest:tt)*) => {
+        $crate::__rule!($typ [$($acc,)* $crate::Antecedent::Is((String::from($c), $crate::HedgedTerm::from($t)))]; $($rest)*)
+    };
+    ($typ:ident [$($acc:expr),*] $c:expr => $t:expr) => {
+        $crate::__rule!($typ [$($acc,)* $crate::Antecedent::Is((String::from($c), $crate::HedgedTerm::from($t)))])
+    };
+    ($typ:ident [$($acc:expr),*] $e:expr, $($rest:tt)*) => {
+        $crate::__rule!($typ [$($acc,)* $e] $($rest)*)
+    };
+    ($typ:ident [$($acc:expr),*] $e:expr; $($rest:tt)*) => {
+        $crate::__rule!($typ [$($acc,)* $e]; $($rest)*)
+    };
+    ($typ:ident [$($acc:expr),*] $e:expr) => {
+        $crate::__rule!($typ [$($acc,)* $e])
+    };
+    // Rule with consequent and weight.
+    ($typ:ident [$($acc:expr),*]; $co:expr => $to:expr; $w:expr) => {
+        $crate::__rule!($typ [$($acc),*]; $co => $to).weight($w)
+    };
+    // Rule with consequent.
+    ($typ:ident [$($acc:expr),*]; $co:expr => $to:expr) => {
+        $crate::FuzzyRule::new(
+            $crate::Antecedent::$typ(vec![$($acc),*]),
+            (String::from($co), $crate::HedgedTerm::from($to))
+        )
+    };
+    // Bare expression.
+    ($typ:ident [$($acc:expr),*]) => {
+        $crate::Antecedent::$typ(vec![$($acc),*])
+    };
+}
+
 // Macro generator for macors.
 macro_rules! make_rule {
     ($d: tt $name:ident, $typ: tt) => {
         #[macro_export]
         #[allow(unused_macros)]
         macro_rules! $name {
-            ($d($d c1:expr=>$d t1:expr),*;$co:expr=>$to:expr) => {
-                $crate::FuzzyRule::$typ(
-                    vec![$d((String::from($d c1), String::from($d t1)),)*],
-                    (String::from($co), String::from($to))
-                )
+            ($d($d body:tt)*) => {
+                $crate::__rule!($typ [] $d($d body)*)
             }
         }
     }
 }
 
+// Rules.
 make_rule!($ and, And);
 make_rule!($ or, Or);
+// Nested expressions.
+make_rule!($ all, And);
+make_rule!($ any, Or);
 
 #[test]
 fn test_macros(
-) -> () {
+) {
+    let is = |c: &str, t: &str| Antecedent::Is((c.to_string(), t.into()));
+    let out = ("change".to_string(), "vol up".into());
     assert_eq!(
         unit!("loudness" => "quiet"; "change" => "keep"),
-        FuzzyRule::Unit(
-            ("loudness".to_string(), "quiet".to_string()),
-            ("change".to_string(), "keep".to_string())
+        FuzzyRule::new(
+            is("loudness", "quiet"),
+            ("change".to_string(), "keep".into())
         )
     );
     assert_eq!(
         and!("loudness" => "quiet", "tod" => "morning", "param1" => "param2"; "change" => "vol up"),
-        FuzzyRule::And(
-            vec![
-                ("loudness".to_string(), "quiet".to_string()),
-                ("tod".to_string(), "morning".to_string()),
-                ("param1".to_string(), "param2".to_string())
-            ],
-            ("change".to_string(), "vol up".to_string()))
+        FuzzyRule::new(
+            Antecedent::And(vec![
+                is("loudness", "quiet"),
+                is("tod", "morning"),
+                is("param1", "param2")
+            ]),
+            out.clone())
     );
     assert_eq!(
         or!("loudness" => "quiet", "tod" => "morning", "param1" => "param2"; "change" => "vol up"),
-        FuzzyRule::Or(
-            vec![
-                ("loudness".to_string(), "quiet".to_string()),
-                ("tod".to_string(), "morning".to_string()),
-                ("param1".to_string(), "param2".to_string())
-            ],
-            ("change".to_string(), "vol up".to_string()))
+        FuzzyRule::new(
+            Antecedent::Or(vec![
+                is("loudness", "quiet"),
+                is("tod", "morning"),
+                is("param1", "param2")
+            ]),
+            out.clone())
     );
     assert_eq!(
         and!("param1" => "param2"; "change" => "vol up"),
-        FuzzyRule::And(
-            vec![
-                ("param1".to_string(), "param2".to_string())
-            ],
-            ("change".to_string(), "vol up".to_string()))
+        FuzzyRule::new(
+            Antecedent::And(vec![
+                is("param1", "param2")
+            ]),
+            out.clone())
+    );
+    assert_eq!(
+        and!("loudness" => !"quiet", "tod" => "morning"; "change" => "vol up"),
+        FuzzyRule::new(
+            Antecedent::And(vec![
+                !is("loudness", "quiet"),
+                is("tod", "morning")
+            ]),
+            out.clone())
+    );
+    assert_eq!(
+        unit!("loudness" => !"quiet"; "change" => "keep"),
+        FuzzyRule::new(
+            Antecedent::Not(Box::new(is("loudness", "quiet"))),
+            ("change".to_string(), "keep".into())
+        )
+    );
+    // (A AND B) OR (C AND NOT D)
+    assert_eq!(
+        or!(all!("a" => "x", "b" => "y"), all!("c" => "z", "d" => !"w"); "change" => "vol up"),
+        FuzzyRule::new(
+            Antecedent::Or(vec![
+                Antecedent::And(vec![is("a", "x"), is("b", "y")]),
+                Antecedent::And(vec![is("c", "z"), !is("d", "w")])
+            ]),
+            out.clone())
+    );
+    assert_eq!(
+        and!("loudness" => "quiet", "tod" => "morning"; "change" => "vol up"; 0.5),
+        FuzzyRule::new(
+            Antecedent::And(vec![
+                is("loudness", "quiet"),
+                is("tod", "morning")
+            ]),
+            out.clone()).weight(0.5)
     );
+    assert_eq!(
+        unit!("loudness" => !"quiet"; "change" => "vol up"; 0.25),
+        FuzzyRule::new(!is("loudness", "quiet"), out.clone()).weight(0.25)
+    );
+    assert_eq!(
+        and!("a" => "x", !any!("b" => "y", "c" => "z"); "change" => "vol up"),
+        FuzzyRule::new(
+            Antecedent::And(vec![
+                is("a", "x"),
+                !Antecedent::Or(vec![is("b", "y"), is("c", "z")])
+            ]),
+            out)
+    );
+}
+
+#[test]
+fn test_round_trip(
+) -> FuzzyResult<()> {
+    use super::membership::{Hedge, very};
+    let is = |c: &str, t: &str| Antecedent::Is((c.to_string(), t.into()));
+    let out = ("d".to_string(), HedgedTerm::from("w"));
+    let power = |p: f64, t: &str| HedgedTerm::from(t).hedge(Hedge::Power(p));
+    // Single item conjunctions and disjunctions are reduced to the item, as parsed.
+    assert_eq!(and!("a" => "x"; "d" => "w"), unit!("a" => "x"; "d" => "w"));
+    assert_eq!(FuzzyRule::new(!Antecedent::Or(vec![is("a", "x")]), out.clone()), unit!("a" => !"x"; "d" => "w"));
+
+    // Every shape of premise survives display and parse.
+    let rules = vec![
+        unit!("a" => "x"; "d" => "w"),
+        unit!("a" => !very("x"); "d" => "w"; 0.25),
+        and!("a" => "x"; "d" => "w"),
+        or!("a" => !"x"; "d" => "w"),
+        and!("a" => "x", "b" => "y"; "d" => "w"),
+        or!("a" => "x", "b" => !"y"; "d" => "w"),
+        and!("a" => "x", any!("b" => "y", all!("c" => "z")); "d" => "w"),
+        or!(all!("a" => "x", "b" => "y"), !any!("c" => "z", "a" => "y"); "d" => "w"),
+        FuzzyRule::new(!Antecedent::And(vec![!is("a", "x")]), out.clone()),
+        FuzzyRule::new(Antecedent::And(vec![Antecedent::And(vec![is("a", "x"), is("b", "y")]), is("c", "z")]), out),
+        unit!("a" => power(1.5, "x").hedge(Hedge::Indeed); "d" => power(0.5, "power")),
+        unit!("say \"hi\"" => "back\\slash"; "d" => "w")
+    ];
+    for rule in rules {
+        assert_eq!(rule.to_string().parse::<FuzzyRule>()?, rule, "{}", rule);
+    }
+    assert_eq!(unit!("a" => power(1.5, "x"); "d" => "power").to_string(), "IF a IS power 1.5 x THEN d IS \"power\"");
+    Ok(())
+}
+
+#[test]
+fn test_operators(
+) -> FuzzyResult<()> {
+    let base = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).fuzzify(
+        "b",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    );
+    let values = crate::values! { "a" => 5.0; "b" => 8.0 };
+    let and = and!("a" => "high", "b" => "high"; "c" => "high");
+    let or = or!("a" => "high", "b" => "high"; "c" => "high");
+
+    assert_eq!(and.apply(&base, &values)?.2, 0.5);
+    assert_eq!(or.apply(&base, &values)?.2, 0.8);
+
+    let fuzzer = base.config(
+        FuzzerConfig::new()
+            .and(TNorm::Product)
+            .or(SNorm::ProbabilisticSum));
+    assert_eq!(and.apply(&fuzzer, &values)?.2, 0.4);
+    assert_eq!(or.apply(&fuzzer, &values)?.2, 0.9);
+
+    let not = and!("a" => !"high", "b" => "high"; "c" => "high");
+    assert_eq!(not.apply(&fuzzer, &values)?.2, 0.4);
+    // (a AND b) OR (NOT a AND NOT b)
+    let nested = or!(all!("a" => "high", "b" => "high"), all!("a" => !"high", "b" => !"high"); "c" => "high");
+    assert_eq!(nested.apply(&fuzzer, &values)?.2, 0.4+0.1-0.04);
+
+    let weighted = and!("a" => "high", "b" => "high"; "c" => "high"; 0.5);
+    assert_eq!(weighted.apply(&fuzzer, &values)?.2, 0.2);
+    let invalid = unit!("a" => "high"; "c" => "high"; 1.5);
+    assert!(matches!(invalid.apply(&fuzzer, &values), Err(FuzzyError::InvalidParameters(_))));
+    let fuzzer = fuzzer.config(FuzzerConfig::new().complement(Complement::Sugeno(1.0)));
+    assert_eq!(not.apply(&fuzzer, &values)?.2, 0.5/1.5);
+    Ok(())
+}
+
+#[test]
+fn test_implication(
+) -> FuzzyResult<()> {
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).defuzzify(
+        "c",
+        crate::fuzzy! { "high" => (0.0, 0.0), (4.0, 1.0), (8.0, 0.0) }?
+    ).rule(unit!("a" => "high"; "c" => "high"));
+    let values = crate::values! { "a" => 5.0 };
+
+    let clipped = fuzzer.apply(&values)?.remove("c").unwrap();
+    assert_eq!(
+        clipped.points("out")?,
+        vec![(0.0, 0.0), (2.0, 0.5), (4.0, 0.5), (6.0, 0.5), (8.0, 0.0)]);
+
+    let fuzzer = fuzzer.config(FuzzerConfig::new().implication(Implication::Product));
+    let scaled = fuzzer.apply(&values)?.remove("c").unwrap();
+    assert_eq!(
+        scaled.points("out")?,
+        vec![(0.0, 0.0), (4.0, 0.5), (8.0, 0.0)]);
+    Ok(())
+}
+
+#[test]
+fn test_aggregation(
+) -> FuzzyResult<()> {
+    let out = |aggregation: Aggregation, x: f64| -> FuzzyResult<f64> {
+        let fuzzer = Fuzzer::new(
+        ).config(
+            FuzzerConfig::new().aggregation(aggregation)
+        ).fuzzify(
+            "a",
+            crate::fuzzy! {
+                "low"  => (0.0, 1.0), (10.0, 0.0);
+                "high" => (0.0, 0.0), (10.0, 1.0)
+            }?
+        ).defuzzify(
+            "c",
+            crate::fuzzy! {
+                "left"  => (0.0, 0.0), (2.0, 1.0), (4.0, 0.0);
+                "right" => (2.0, 0.0), (4.0, 1.0), (6.0, 0.0)
+            }?
+        ).rule(unit!("a" => "low"; "c" => "left")
+        ).rule(unit!("a" => "high"; "c" => "left")
+        ).rule(unit!("a" => "high"; "c" => "right"));
+        let values = crate::values! { "a" => 5.0 };
+        fuzzer.apply(&values)?.remove("c").unwrap().call_single("out", x)
+    };
+
+    // Both terms clipped at 0.5, they overlap at x=3.
+    assert_eq!(out(Aggregation::Max, 1.0)?, 0.5);
+    assert_eq!(out(Aggregation::Max, 3.0)?, 0.5);
+    assert_eq!(out(Aggregation::BoundedSum, 1.0)?, 1.0);
+    assert_eq!(out(Aggregation::BoundedSum, 3.0)?, 1.0);
+    assert_eq!(out(Aggregation::ProbabilisticOr, 1.0)?, 0.75);
+    assert_eq!(out(Aggregation::ProbabilisticOr, 3.0)?, 0.875);
+    // Sum peaks at 1.5 in x=3.
+    assert_eq!(out(Aggregation::NormalizedSum, 3.0)?, 1.0);
+    assert_eq!(out(Aggregation::NormalizedSum, 1.0)?, 1.0/1.5);
+    let custom = Aggregation::Custom(std::sync::Arc::new(|a: f64, b: f64| a.min(b)));
+    assert_eq!(out(custom, 3.0)?, 0.0);
+    Ok(())
+}
+
+#[test]
+fn test_evaluate(
+) -> FuzzyResult<()> {
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).defuzzify(
+        "c",
+        crate::fuzzy! {
+            "left"  => (0.0, 0.0), (2.0, 1.0), (4.0, 0.0);
+            "right" => (4.0, 0.0), (6.0, 1.0), (8.0, 0.0)
+        }?
+    ).rule(unit!("a" => "high"; "c" => "left")
+    ).rule(unit!("a" => "high"; "c" => "right"));
+    let values = crate::values! { "a" => 5.0 };
+
+    let cog = fuzzer.evaluate(&values)?.remove("c").unwrap();
+    assert!((cog-4.0).abs() < 1e-9);
+    let fuzzer = fuzzer.config(FuzzerConfig::new().defuzzifier(Defuzzifier::FirstOfMaxima));
+    assert_eq!(fuzzer.evaluate(&values)?.remove("c"), Some(1.0));
+
+    let fuzzer = fuzzer.defuzzify_with(
+        "d",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?,
+        Defuzzifier::LastOfMaxima
+    ).rule(unit!("a" => "high"; "d" => "high"));
+    let mut outputs = fuzzer.evaluate(&values)?;
+    assert_eq!(outputs.remove("c"), Some(1.0));
+    assert_eq!(outputs.remove("d"), Some(10.0));
+
+    let values = crate::values! { "a" => 0.0 };
+    assert!(matches!(fuzzer.evaluate(&values), Err(FuzzyError::NoRuleFired(_))));
+    Ok(())
+}
+
+#[test]
+fn test_shoulder(
+) -> FuzzyResult<()> {
+    let evaluate = |defuzzifier: Defuzzifier| -> FuzzyResult<f64> {
+        let fuzzer = Fuzzer::new(
+        ).fuzzify(
+            "a",
+            crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+        ).defuzzify_with(
+            "c",
+            crate::fuzzy! { "low" => (0.0, 1.0), (2.0, 1.0), (4.0, 0.0) }?,
+            defuzzifier
+        ).rule(unit!("a" => "high"; "c" => "low"));
+        Ok(fuzzer.evaluate(&crate::values! { "a" => 5.0 })?["c"])
+    };
+
+    // Clipped at 0.5, the plateau spans [0, 3]: (1.5*1.5 + 0.25*(3+1/3)) / 1.75.
+    let cog = 37.0/21.0;
+    assert!((evaluate(Defuzzifier::Centroid)?-cog).abs() < 1e-9);
+    assert!((evaluate(Defuzzifier::CenterOfSums)?-cog).abs() < 1e-9);
+    assert_eq!(evaluate(Defuzzifier::Height)?, 1.5);
+    Ok(())
+}
+
+#[test]
+fn test_periodic(
+) -> FuzzyResult<()> {
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).defuzzify(
+        "heading",
+        FuzzySet::new()
+            .periodic(360.0)?
+            .term("north", crate::membership::MembershipFunction::Triangular { a: -30.0, b: 0.0, c: 30.0 })?
+            .term("east", crate::membership::MembershipFunction::Triangular { a: 60.0, b: 90.0, c: 120.0 })?
+    ).rule(unit!("a" => "high"; "heading" => "north"));
+
+    let heading = fuzzer.evaluate(&crate::values! { "a" => 5.0 })?["heading"];
+    assert!(heading.min(360.0-heading) < 1e-9);
+    let set = fuzzer.apply(&crate::values! { "a" => 5.0 })?.remove("heading").unwrap();
+    assert_eq!(set.period(), Some(360.0));
+    assert_eq!(set.call_single(OUTPUT_TERM, -15.0)?, 0.5);
+    Ok(())
+}
+
+#[test]
+fn test_sugeno(
+) -> FuzzyResult<()> {
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! {
+            "low"  => (0.0, 1.0), (10.0, 0.0);
+            "high" => (0.0, 0.0), (10.0, 1.0)
+        }?
+    ).sugeno(
+        "c",
+        SugenoSet::new()
+            .constant("zero", 0.0)
+            .linear("double", vec![("a", 2.0)], 1.0)
+    ).rule(unit!("a" => "low"; "c" => "zero")
+    ).rule(unit!("a" => "high"; "c" => "double"));
+
+    // 0.2*0 + 0.8*(2*8+1)
+    let values = crate::values! { "a" => 8.0 };
+    let y = fuzzer.evaluate(&values)?.remove("c").unwrap();
+    assert!((y-0.8*17.0).abs() < 1e-9);
+    assert!(fuzzer.apply(&values)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_tsukamoto(
+) -> FuzzyResult<()> {
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! {
+            "low"  => (0.0, 1.0), (10.0, 0.0);
+            "high" => (0.0, 0.0), (10.0, 1.0)
+        }?
+    ).tsukamoto(
+        "c",
+        crate::fuzzy! {
+            "down" => (0.0, 1.0), (4.0, 0.0);
+            "up"   => (0.0, 0.0), (2.0, 0.0), (6.0, 1.0)
+        }?
+    )?.rule(unit!("a" => "low"; "c" => "down")
+    ).rule(unit!("a" => "high"; "c" => "up"));
+
+    // down^-1(0.25) = 3, up^-1(0.75) = 5
+    let values = crate::values! { "a" => 7.5 };
+    assert_eq!(fuzzer.evaluate(&values)?.remove("c"), Some(0.25*3.0+0.75*5.0));
+
+    // Inverted within the universe: "up" tops out at 0.5 on [0, 10].
+    let bounded = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).tsukamoto(
+        "c",
+        crate::fuzzy! { "up" => (-10.0, 0.0), (30.0, 1.0) }?.universe(0.0, 10.0)?
+    )?.rule(unit!("a" => "high"; "c" => "up"));
+    assert_eq!(bounded.evaluate(&values)?.remove("c"), Some(10.0));
+
+    let invalid = Fuzzer::new().tsukamoto(
+        "c",
+        crate::fuzzy! { "peak" => (0.0, 0.0), (1.0, 1.0), (2.0, 0.0) }?
+    );
+    assert_eq!(invalid.err(), Some(FuzzyError::NonMonotonicTerm("peak".to_string()).in_category("c")));
+    Ok(())
+}
+
+#[test]
+fn test_hedges(
+) -> FuzzyResult<()> {
+    use super::membership::{very, somewhat};
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "a",
+        crate::fuzzy! { "high" => (0.0, 0.0), (10.0, 1.0) }?
+    ).tsukamoto(
+        "c",
+        crate::fuzzy! { "up" => (0.0, 0.0), (10.0, 1.0) }?
+    )?.rule(unit!("a" => very("high"); "c" => somewhat("up")));
+
+    // very(0.5) = 0.25, somewhat up^-1(0.25) = 0.0625*10, inverted on sampled curve.
+    let values = crate::values! { "a" => 5.0 };
+    let fired = fuzzer.fire(&values)?;
+    assert_eq!(fired[0].1, somewhat("up"));
+    assert_eq!(fired[0].2, 0.25);
+    assert!((fuzzer.evaluate(&values)?["c"]-0.625).abs() < 1e-2);
+
+    let parsed = Fuzzer::new().rules_from_str("IF a IS very high THEN c IS somewhat up")?;
+    assert_eq!(parsed.rules, fuzzer.rules);
+
+    let rule = or!("a" => very("high"), "a" => !somewhat("high"); "c" => "up");
+    assert_eq!(rule.apply(&fuzzer, &values)?.2, 0.25f64.max(1.0-0.5f64.sqrt()));
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde(
+) -> FuzzyResult<()> {
+    let json = r#"{
+        "inputs": {
+            "a": { "terms": {
+                "low": { "polyline": [[0.0, 1.0], [10.0, 0.0]] },
+                "high": { "triangular": { "a": 0.0, "b": 10.0, "c": 20.0 } }
+            } }
+        },
+        "outputs": {
+            "c": { "universe": [0.0, 10.0], "terms": { "up": { "hedged": ["very", { "s": { "a": 0.0, "b": 10.0 } }] } } }
+        },
+        "defuzzifiers": { "c": "bisector" },
+        "sugeno": { "d": { "one": { "constant": 1.0 }, "two": { "linear": [{ "a": 0.5 }, 0.0] } } },
+        "rules": [
+            { "antecedent": { "is": ["a", { "term": "low" }] }, "consequent": ["c", { "term": "up" }] },
+            {
+                "antecedent": { "or": [
+                    { "not": { "is": ["a", { "term": "low", "hedges": ["very"] }] } },
+                    { "is": ["a", { "term": "high" }] }
+                ] },
+                "consequent": ["d", { "term": "two" }],
+                "weight": 0.5
+            },
+            {
+                "antecedent": { "and": [{ "is": ["a", { "term": "low" }] }, { "is": ["a", { "term": "high" }] }] },
+                "consequent": ["d", { "term": "one" }],
+                "weight": 0.1
+            }
+        ],
+        "config": { "and": { "hamacher": 2.0 }, "aggregation": "probabilistic_or" }
+    }"#;
+    let parse = |json: &str| serde_json::from_str::<Fuzzer>(json).map_err(|e| FuzzyError::Misc(e.to_string()));
+    let fuzzer = parse(json)?;
+    assert_eq!(fuzzer.rules[1].weight, 0.5);
+    assert_eq!(fuzzer.rules[0].weight, 1.0);
+
+    let serialized = serde_json::to_string(&fuzzer).unwrap();
+    let restored = parse(&serialized)?;
+    assert_eq!(serde_json::to_string(&restored).unwrap(), serialized);
+    assert_eq!(restored.rules, fuzzer.rules);
+    let values = crate::values! { "a" => 4.0 };
+    assert_eq!(restored.evaluate(&values)?, fuzzer.evaluate(&values)?);
+
+    // Sets and the whole Fuzzer are validated and custom operators can't be serialized.
+    assert!(parse(r#"{ "inputs": { "a": { "terms": { "x": { "polyline": [[0.0, 1.0]] } } } } }"#).is_err());
+    // Sets are objects with terms, so a term named "terms" is unambiguous.
+    let set = serde_json::from_str::<FuzzySet>(r#"{ "terms": { "terms": { "polyline": [[0.0, 1.0], [1.0, 0.0]] } } }"#).unwrap();
+    assert!(set.function("terms").is_ok());
+    assert!(serde_json::from_str::<FuzzySet>(r#"{ "low": { "polyline": [[0.0, 1.0], [1.0, 0.0]] } }"#).is_err());
+    let tsukamoto = r#"{ "tsukamoto": { "c": { "terms": { "peak": { "polyline": [[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]] } } } } }"#;
+    assert_eq!(parse(tsukamoto).err(), Some(FuzzyError::Misc(
+        FuzzyError::NonMonotonicTerm("peak".to_string()).in_category("c").to_string()
+    )));
+    let rules = r#"{ "rules": [{ "antecedent": { "or": [] }, "consequent": ["c", { "term": "up" }] }] }"#;
+    assert!(parse(rules).is_err());
+    assert!(parse(r#"{ "config": { "complement": { "yager": 0.0 } } }"#).is_err());
+    let custom = Fuzzer::new().config(FuzzerConfig::new().and(TNorm::Custom(std::sync::Arc::new(f64::min))));
+    assert!(serde_json::to_string(&custom).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_validate(
+) -> FuzzyResult<()> {
+    let fuzzer = Fuzzer::new(
+    ).fuzzify(
+        "loudness",
+        crate::fuzzy! { "quiet" => (0.0, 1.0), (10.0, 0.0) }?
+    ).defuzzify(
+        "change",
+        crate::fuzzy! { "vol up" => (0.0, 0.0), (10.0, 1.0) }?
+    ).sugeno(
+        "gain",
+        SugenoSet::new().constant("one", 1.0).linear("scaled", vec![("loudness", 0.5), ("pitch", 1.0)], 0.0)
+    ).rule(unit!("loudness" => "quiet"; "change" => "vol up")
+    ).rule(and!("loudnes" => "quiet", "loudness" => !"silent"; "change" => "vol down")
+    ).rule(unit!("loudness" => "quiet"; "gain" => super::membership::very("one"); 1.5)
+    ).rule(unit!("loudness" => "quiet"; "volume" => "up")
+    ).rule(FuzzyRule::new(
+        Antecedent::Or(vec![Antecedent::And(vec![]), !Antecedent::Or(vec![])]),
+        ("change".to_string(), "vol up".into())
+    )).rule(unit!("loudness" => "quiet"; "gain" => "scaled"));
+    assert_eq!(fuzzer.validate(), Err(FuzzyError::InvalidRules(vec![
+        (1, FuzzyError::InvalidCategory("loudnes".to_string())),
+        (1, FuzzyError::InvalidTerm("silent".to_string())),
+        (1, FuzzyError::InvalidTerm("vol down".to_string())),
+        (2, FuzzyError::InvalidTerm("very one".to_string())),
+        (2, FuzzyError::InvalidParameters("rule weight 1.5 out of [0, 1]".to_string())),
+        (3, FuzzyError::InvalidCategory("volume".to_string())),
+        (4, FuzzyError::InvalidParameters("and without operands".to_string())),
+        (4, FuzzyError::InvalidParameters("or without operands".to_string())),
+        (5, FuzzyError::InvalidCategory("pitch".to_string()))
+    ])));
+    let fuzzer = Fuzzer { rules: fuzzer.rules[..1].to_vec(), ..fuzzer };
+    assert_eq!(fuzzer.validate(), Ok(()));
+    let fuzzer = fuzzer.config(FuzzerConfig::new().implication(Implication::Norm(TNorm::Hamacher(-0.5))));
+    assert_eq!(fuzzer.validate(), Err(FuzzyError::InvalidParameters("Hamacher(-0.5)".to_string())));
+    let fuzzer = fuzzer.config(FuzzerConfig::new().complement(Complement::Yager(-2.0)));
+    assert_eq!(fuzzer.validate(), Err(FuzzyError::InvalidParameters("Yager(-2.0)".to_string())));
+    Ok(())
 }
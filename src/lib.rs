@@ -1,12 +1,21 @@
 #![allow(unused_imports)]
 
 pub mod set;
+pub mod membership;
+pub mod ops;
 pub mod plot;
 pub mod fuzz;
 pub mod defuzz;
+pub mod sugeno;
+pub mod parse;
+pub mod fcl;
+pub mod fis;
+pub mod explain;
 pub mod common;
 
 use set::*;
+use membership::*;
+use ops::*;
 use fuzz::*;
 use common::*;
 
@@ -96,12 +105,13 @@ fn test_fuzzer(
         .remove("change")
         .unwrap();
     // Apply defuzzification method
-    let cog = defuzz::cog(change.points("out")?)?;
+    let cog = defuzz::cog(change.points(OUTPUT_TERM)?)?;
     plot::set(&change, format!("COG: {:.2}", cog), "Y").to_svg("test3.svg")?;
 
-    println!("COG: {:?}", cog) ;
-    println!("{:?}", change);
-
-    assert!(false);
+    // Only "loud" (1.0) with "evening" and "night" (0.5 each) fire, both into "vol down",
+    // which clipped at 0.5 is a rectangle over [0, 3] and a triangle over [3, 4]:
+    // (1.5*1.5 + 0.25*(3+1/3)) / (1.5+0.25) = 37/21.
+    assert!((cog-37.0/21.0).abs() < 1e-9);
+    assert_eq!(fuzzer.evaluate(&input)?.remove("change"), Some(cog));
     Ok(())
 }
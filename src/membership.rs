@@ -0,0 +1,583 @@
+use std::fmt;
+
+use super::common::{FuzzyError, FuzzyResult, Term};
+
+/// Number of points used when a parametric function has to be turned into a polyline.
+pub const SAMPLES: usize = 101;
+
+/// Shape of a single term's membership curve.
+///
+/// Parametric shapes follow the MATLAB Fuzzy Logic Toolbox definitions
+/// (trimf, trapmf, gaussmf, gbellmf, sigmf, smf, zmf, pimf).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MembershipFunction {
+    ///   /\
+    ///  /  \
+    /// a  b  c
+    Triangular { a: f64, b: f64, c: f64 },
+    ///   / - \
+    ///  /     \
+    /// a b   c d
+    Trapezoidal { a: f64, b: f64, c: f64, d: f64 },
+    /// exp(-(x-mean)^2/(2*sigma^2))
+    Gaussian { mean: f64, sigma: f64 },
+    /// 1/(1+|(x-c)/a|^(2b)), a != 0, b > 0
+    Bell { a: f64, b: f64, c: f64 },
+    /// 1/(1+exp(-a(x-c))), a != 0
+    Sigmoid { a: f64, c: f64 },
+    /// Spline-based curve rising from 0 at a to 1 at b.
+    S { a: f64, b: f64 },
+    /// Spline-based curve falling from 1 at a to 0 at b.
+    Z { a: f64, b: f64 },
+    /// S curve from a to b followed by Z curve from c to d.
+    Pi { a: f64, b: f64, c: f64, d: f64 },
+    /// Piecewise-linear curve, points ordered by x axis.
+    Polyline(Vec<(f64, f64)>),
+    /// Other function modified by linguistic hedge.
+    Hedged(Hedge, Box<MembershipFunction>)
+}
+
+/// What to do with polyline memberships outside of [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointPolicy {
+    /// Reject the function with `FuzzyError::MembershipOutOfRange`.
+    #[default]
+    Strict,
+    /// Cut memberships to [0, 1].
+    Clamp,
+    /// Linearly map memberships from [min(0, lowest), max(1, highest)] to [0, 1],
+    /// so that valid curves are kept as they are and the shape of others is preserved.
+    Normalize
+}
+
+impl MembershipFunction {
+    /// Checks parameters and brings function to canonical form.
+    pub fn validate(
+        self
+    ) -> FuzzyResult<Self> {
+        self.validate_with(PointPolicy::Strict)
+    }
+
+    /// Same as `validate`, but memberships outside of [0, 1] are handled according to policy.
+    /// Non-finite points and duplicate x values with different memberships are always rejected,
+    /// exact duplicates are merged.
+    pub fn validate_with(
+        self,
+        policy: PointPolicy
+    ) -> FuzzyResult<Self> {
+        use MembershipFunction::*;
+        let ordered = |xs: &[f64]| xs.iter().all(|x| x.is_finite()) && xs.windows(2).all(|w| w[0] <= w[1]);
+        let finite = |xs: &[f64]| xs.iter().all(|x| x.is_finite());
+        let valid = match &self {
+            Triangular { a, b, c } => ordered(&[*a, *b, *c]),
+            Trapezoidal { a, b, c, d } | Pi { a, b, c, d } => ordered(&[*a, *b, *c, *d]),
+            Gaussian { mean, sigma } => finite(&[*mean, *sigma]) && *sigma > 0.0,
+            Bell { a, b, c } => finite(&[*a, *b, *c]) && *a != 0.0 && *b > 0.0,
+            Sigmoid { a, c } => finite(&[*a, *c]) && *a != 0.0,
+            S { a, b } | Z { a, b } => ordered(&[*a, *b]),
+            Polyline(points) => {
+                if points.len() < 2 {
+                    Err(FuzzyError::InvalidPoints(format!("polyline needs at least 2 points, got {}", points.len())))?
+                }
+                if let Some(&(x, y)) = points.iter().find(|(x, y)| !x.is_finite() || !y.is_finite()) {
+                    Err(FuzzyError::NonFinitePoint { x, y })?
+                }
+                true
+            },
+            Hedged(Hedge::Power(power), _) => power.is_finite() && *power > 0.0,
+            Hedged(_, _) => true
+        };
+        // Extreme widths overflow the sampled range.
+        let (low, high) = self.range();
+        if !valid || !(high-low).is_finite() {
+            Err(FuzzyError::InvalidParameters(format!("{:?}", self)))?
+        }
+        match self {
+            Polyline(mut points) => {
+                let low = points.iter().fold(0.0, |low: f64, (_, y)| low.min(*y));
+                let high = points.iter().fold(1.0, |high: f64, (_, y)| high.max(*y));
+                for (x, y) in points.iter_mut() {
+                    *y = match policy {
+                        PointPolicy::Strict if *y < 0.0 || *y > 1.0 =>
+                            Err(FuzzyError::MembershipOutOfRange { x: *x, y: *y })?,
+                        PointPolicy::Strict => *y,
+                        PointPolicy::Clamp => y.clamp(0.0, 1.0),
+                        PointPolicy::Normalize => (*y-low)/(high-low)
+                    };
+                }
+                // Order points by x axis, coordinates are finite at this point.
+                points.sort_by(|(x1, _), (x2, _)| x1.total_cmp(x2));
+                points.dedup();
+                if let Some(window) = points.windows(2).find(|w| w[0].0 == w[1].0) {
+                    Err(FuzzyError::ConflictingPoints { x: window[0].0, first: window[0].1, second: window[1].1 })?
+                }
+                if points.len() < 2 {
+                    Err(FuzzyError::InvalidPoints(format!("polyline needs at least 2 distinct points, got {}", points.len())))?
+                }
+                Ok(Polyline(points))
+            },
+            Hedged(hedge, inner) => Ok(Hedged(hedge, Box::new(inner.validate_with(policy)?))),
+            other => Ok(other)
+        }
+    }
+
+    /// Evaluates membership of x.
+    pub fn call(
+        &self,
+        x: f64
+    ) -> f64 {
+        use MembershipFunction::*;
+        match self {
+            Triangular { a, b, c } =>
+                Self::trapezoid(x, *a, *b, *b, *c),
+            Trapezoidal { a, b, c, d } =>
+                Self::trapezoid(x, *a, *b, *c, *d),
+            Gaussian { mean, sigma } =>
+                (-(x-mean).powi(2)/(2.0*sigma.powi(2))).exp(),
+            Bell { a, b, c } =>
+                1.0/(1.0+((x-c)/a).abs().powf(2.0*b)),
+            Sigmoid { a, c } =>
+                1.0/(1.0+(-a*(x-c)).exp()),
+            S { a, b } =>
+                Self::s_curve(x, *a, *b),
+            Z { a, b } =>
+                1.0-Self::s_curve(x, *a, *b),
+            Pi { a, b, c, d } =>
+                if x <= *b { Self::s_curve(x, *a, *b) } else { 1.0-Self::s_curve(x, *c, *d) },
+            Polyline(points) =>
+                Self::interpolate(points, x),
+            Hedged(hedge, inner) =>
+                hedge.call(inner.call(x))
+        }
+    }
+
+    /// Interval outside of which function is (nearly) constant.
+    pub fn range(
+        &self
+    ) -> (f64, f64) {
+        use MembershipFunction::*;
+        match self {
+            Triangular { a, c, .. } => (*a, *c),
+            Trapezoidal { a, d, .. } | Pi { a, d, .. } => (*a, *d),
+            // exp(-8) ~ 3e-4 at the boundaries.
+            Gaussian { mean, sigma } => (mean-4.0*sigma, mean+4.0*sigma),
+            // 1/(1+999) = 1e-3 at the boundaries.
+            Bell { a, b, c } => {
+                let width = a.abs()*999f64.powf(1.0/(2.0*b));
+                (c-width, c+width)
+            },
+            // 1/(1+exp(7)) ~ 1e-3 at the boundaries.
+            Sigmoid { a, c } => (c-7.0/a.abs(), c+7.0/a.abs()),
+            S { a, b } | Z { a, b } => (*a, *b),
+            Polyline(points) => (points.first().unwrap().0, points.last().unwrap().0),
+            Hedged(_, inner) => inner.range()
+        }
+    }
+
+    /// Memberships as x goes to -infinity and infinity.
+    pub(crate) fn limits(
+        &self
+    ) -> (f64, f64) {
+        use MembershipFunction::*;
+        match self {
+            Triangular { .. } | Trapezoidal { .. } | Pi { .. } | Gaussian { .. } | Bell { .. } => (0.0, 0.0),
+            Sigmoid { a, .. } => if *a > 0.0 { (0.0, 1.0) } else { (1.0, 0.0) },
+            S { .. } => (0.0, 1.0),
+            Z { .. } => (1.0, 0.0),
+            Polyline(points) => (points.first().unwrap().1, points.last().unwrap().1),
+            Hedged(hedge, inner) => {
+                let (low, high) = inner.limits();
+                (hedge.call(low), hedge.call(high))
+            }
+        }
+    }
+
+    /// Piecewise-linear representation of the function.
+    /// Exact for polylines, triangles and trapezoids, sampled otherwise.
+    pub fn points(
+        &self
+    ) -> Vec<(f64, f64)> {
+        use MembershipFunction::*;
+        match self {
+            Triangular { a, b, c } => self.vertices(&[*a, *b, *c]),
+            Trapezoidal { a, b, c, d } => self.vertices(&[*a, *b, *c, *d]),
+            Polyline(points) => points.clone(),
+            Hedged(_, inner) => {
+                let (from, to) = self.range();
+                let step = (to-from)/(SAMPLES-1) as f64;
+                // Keep vertices of the inner function, so that sampling never cuts corners.
+                let mut xs = inner.points()
+                    .into_iter()
+                    .map(|(x, _)| x)
+                    .chain((1..SAMPLES-1).map(|i| from+step*i as f64))
+                    .collect::<Vec<f64>>();
+                xs.sort_by(|a, b| a.total_cmp(b));
+                xs.dedup();
+                xs.into_iter().map(|x| (x, self.call(x))).collect()
+            },
+            _ => {
+                let (from, to) = self.range();
+                let step = (to-from)/(SAMPLES-1) as f64;
+                (0..SAMPLES)
+                    .map(|i| if i == SAMPLES-1 { to } else { from+step*i as f64 })
+                    .map(|x| (x, self.call(x)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Points at given x values, coinciding ones (degenerate sides) are merged.
+    fn vertices(
+        &self,
+        xs: &[f64]
+    ) -> Vec<(f64, f64)> {
+        let mut xs = xs.to_vec();
+        xs.dedup();
+        xs.into_iter().map(|x| (x, self.call(x))).collect()
+    }
+
+    /// Linear interpolation between points. Beyond the boundaries first/last y is kept.
+    pub(crate) fn interpolate(
+        points: &[(f64, f64)],
+        x: f64
+    ) -> f64 {
+        let (mut lx, mut ly) = *points.first().unwrap();
+        let (mut rx, mut ry) = *points.last().unwrap();
+        if x < lx {
+            rx = lx; ry = ly;
+        } else if x > rx {
+            lx = rx; ly = ry;
+        } else {
+            for window in points.windows(2) {
+                let (px, py) = window[0];
+                let (cx, cy) = window[1];
+                if px <= x && cx >= x {
+                    lx = px; ly = py;
+                    rx = cx; ry = cy;
+                    break;
+                }
+            }
+        }
+        let slope = if lx == rx  { 0.0 } else { (ly-ry)/(lx-rx) };
+        ly+(x-lx)*slope
+    }
+
+    fn trapezoid(
+        x: f64,
+        a: f64,
+        b: f64,
+        c: f64,
+        d: f64
+    ) -> f64 {
+        if x < a || x > d {
+            0.0
+        } else if x < b {
+            (x-a)/(b-a)
+        } else if x <= c {
+            1.0
+        } else {
+            (d-x)/(d-c)
+        }
+    }
+
+    fn s_curve(
+        x: f64,
+        a: f64,
+        b: f64
+    ) -> f64 {
+        if x <= a {
+            0.0
+        } else if x >= b {
+            1.0
+        } else if x <= (a+b)/2.0 {
+            2.0*((x-a)/(b-a)).powi(2)
+        } else {
+            1.0-2.0*((x-b)/(b-a)).powi(2)
+        }
+    }
+}
+
+impl From<Vec<(f64, f64)>> for MembershipFunction {
+    fn from(
+        points: Vec<(f64, f64)>
+    ) -> Self {
+        MembershipFunction::Polyline(points)
+    }
+}
+
+/// Linguistic hedge modifying membership of a term.
+/// Exponents follow Negnevitsky, "Artificial Intelligence: A Guide to Intelligent Systems".
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Hedge {
+    /// Concentration, y^2.
+    Very,
+    /// Concentration, y^3.
+    Extremely,
+    /// Concentration, y^1.7.
+    Slightly,
+    /// Dilation, y^0.5.
+    Somewhat,
+    /// Dilation, y^0.5.
+    MoreOrLess,
+    /// Contrast intensification, 2y^2 for y <= 0.5, 1-2(1-y)^2 otherwise.
+    Indeed,
+    /// Custom exponent.
+    Power(f64)
+}
+
+impl Hedge {
+    pub fn call(
+        &self,
+        y: f64
+    ) -> f64 {
+        match self {
+            Hedge::Very => y.powi(2),
+            Hedge::Extremely => y.powi(3),
+            Hedge::Slightly => y.powf(1.7),
+            Hedge::Somewhat | Hedge::MoreOrLess => y.sqrt(),
+            Hedge::Indeed =>
+                if y <= 0.5 { 2.0*y.powi(2) } else { 1.0-2.0*(1.0-y).powi(2) },
+            Hedge::Power(p) => y.powf(*p)
+        }
+    }
+}
+
+impl fmt::Display for Hedge {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        match self {
+            Hedge::Very => write!(f, "very"),
+            Hedge::Extremely => write!(f, "extremely"),
+            Hedge::Slightly => write!(f, "slightly"),
+            Hedge::Somewhat => write!(f, "somewhat"),
+            Hedge::MoreOrLess => write!(f, "more or less"),
+            Hedge::Indeed => write!(f, "indeed"),
+            Hedge::Power(p) => write!(f, "power {}", p)
+        }
+    }
+}
+
+/// Reference to a term with hedges applied to it.
+/// Hedges are stored in order of application, the innermost first.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HedgedTerm {
+    pub term: Term,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub hedges: Vec<Hedge>
+}
+
+impl HedgedTerm {
+    /// Wraps term with another hedge.
+    pub fn hedge(
+        mut self,
+        hedge: Hedge
+    ) -> Self {
+        self.hedges.push(hedge);
+        self
+    }
+
+    /// Applies hedges to the function of referenced term.
+    pub fn apply(
+        &self,
+        function: MembershipFunction
+    ) -> MembershipFunction {
+        self.hedges.iter().fold(function, |inner, hedge| {
+            MembershipFunction::Hedged(*hedge, Box::new(inner))
+        })
+    }
+}
+
+impl From<&str> for HedgedTerm {
+    fn from(
+        term: &str
+    ) -> Self {
+        Self::from(Term::from(term))
+    }
+}
+
+impl From<Term> for HedgedTerm {
+    fn from(
+        term: Term
+    ) -> Self {
+        Self { term, hedges: Vec::new() }
+    }
+}
+
+impl From<&Term> for HedgedTerm {
+    fn from(
+        term: &Term
+    ) -> Self {
+        Self::from(term.clone())
+    }
+}
+
+impl fmt::Display for HedgedTerm {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        for hedge in self.hedges.iter().rev() {
+            write!(f, "{} ", hedge)?;
+        }
+        write!(f, "{}", self.term)
+    }
+}
+
+pub fn very(
+    term: impl Into<HedgedTerm>
+) -> HedgedTerm {
+    term.into().hedge(Hedge::Very)
+}
+
+pub fn extremely(
+    term: impl Into<HedgedTerm>
+) -> HedgedTerm {
+    term.into().hedge(Hedge::Extremely)
+}
+
+pub fn slightly(
+    term: impl Into<HedgedTerm>
+) -> HedgedTerm {
+    term.into().hedge(Hedge::Slightly)
+}
+
+pub fn somewhat(
+    term: impl Into<HedgedTerm>
+) -> HedgedTerm {
+    term.into().hedge(Hedge::Somewhat)
+}
+
+pub fn more_or_less(
+    term: impl Into<HedgedTerm>
+) -> HedgedTerm {
+    term.into().hedge(Hedge::MoreOrLess)
+}
+
+pub fn indeed(
+    term: impl Into<HedgedTerm>
+) -> HedgedTerm {
+    term.into().hedge(Hedge::Indeed)
+}
+
+/// Combines two polylines point by point.
+/// Points where curves cross each other or where their sum crosses 1 are inserted,
+/// so the result is exact for min, max and (bounded) sums.
+pub fn combine(
+    a: &[(f64, f64)],
+    b: &[(f64, f64)],
+    op: impl Fn(f64, f64) -> f64
+) -> Vec<(f64, f64)> {
+    let mut xs = a.iter().chain(b.iter())
+        .map(|(x, _)| *x)
+        .collect::<Vec<f64>>();
+    xs.sort_by(|x, y| x.total_cmp(y));
+    xs.dedup();
+
+    let at = |x: f64| (MembershipFunction::interpolate(a, x), MembershipFunction::interpolate(b, x));
+    let mut points = Vec::with_capacity(xs.len());
+    for (i, x) in xs.iter().enumerate() {
+        if i > 0 {
+            let x0 = xs[i-1];
+            let (a0, b0) = at(x0);
+            let (a1, b1) = at(*x);
+            let mut crossings = [(a0-b0, a1-b1), (a0+b0-1.0, a1+b1-1.0)]
+                .iter()
+                .filter(|(d0, d1)| d0*d1 < 0.0)
+                .map(|(d0, d1)| x0+(x-x0)*d0/(d0-d1))
+                .collect::<Vec<f64>>();
+            crossings.sort_by(|x, y| x.total_cmp(y));
+            for cx in crossings {
+                let (ya, yb) = at(cx);
+                points.push((cx, op(ya, yb)));
+            }
+        }
+        let (ya, yb) = at(*x);
+        points.push((*x, op(ya, yb)));
+    }
+    points
+}
+
+#[test]
+fn test_membership(
+) {
+    use MembershipFunction::*;
+    assert_eq!(Triangular { a: 0.0, b: 1.0, c: 3.0 }.call(2.0), 0.5);
+    assert_eq!(Triangular { a: 0.0, b: 0.0, c: 2.0 }.call(0.0), 1.0);
+    assert_eq!(Trapezoidal { a: 0.0, b: 1.0, c: 2.0, d: 4.0 }.call(1.5), 1.0);
+    assert_eq!(Gaussian { mean: 5.0, sigma: 2.0 }.call(5.0), 1.0);
+    assert_eq!(Bell { a: 2.0, b: 1.0, c: 5.0 }.call(7.0), 0.5);
+    assert_eq!(Sigmoid { a: 3.0, c: 1.0 }.call(1.0), 0.5);
+    assert_eq!(S { a: 0.0, b: 2.0 }.call(1.0), 0.5);
+    assert_eq!(Z { a: 0.0, b: 2.0 }.call(0.5), 0.875);
+    assert_eq!(Pi { a: 0.0, b: 1.0, c: 2.0, d: 3.0 }.call(1.5), 1.0);
+    assert_eq!(Polyline(vec![(0.0, 0.0), (2.0, 1.0)]).call(1.0), 0.5);
+
+    let points = Gaussian { mean: 0.0, sigma: 1.0 }.points();
+    assert_eq!(points.len(), SAMPLES);
+    let (x, y) = points[SAMPLES/2];
+    assert!(x.abs() < 1e-9 && (y-1.0).abs() < 1e-9);
+
+    assert_eq!(
+        Gaussian { mean: 0.0, sigma: 0.0 }.validate(),
+        Err(FuzzyError::InvalidParameters("Gaussian { mean: 0.0, sigma: 0.0 }".to_string())));
+    // Flat sigmoid and bell have no range to sample, negative bell slope turns it upside down.
+    assert!(matches!(Sigmoid { a: 0.0, c: 1.0 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+    assert!(matches!(Bell { a: 1.0, b: 0.0, c: 1.0 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+    assert!(matches!(Bell { a: 1.0, b: -1.0, c: 1.0 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+    // Widths overflowing the range.
+    assert!(matches!(Gaussian { mean: 0.0, sigma: 1e308 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+    assert!(matches!(Bell { a: 1e308, b: 1.0, c: 0.0 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+    assert!(matches!(Sigmoid { a: 1e-320, c: 0.0 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+    assert_eq!(Polyline(vec![(0.0, 0.0)]).validate(), Err(FuzzyError::InvalidPoints("polyline needs at least 2 points, got 1".to_string())));
+}
+
+#[test]
+fn test_hedges(
+) {
+    let term = very(somewhat("loud"));
+    assert_eq!(term.hedges, vec![Hedge::Somewhat, Hedge::Very]);
+    assert_eq!(term.to_string(), "very somewhat loud");
+
+    let function = very(indeed("x")).apply(MembershipFunction::Polyline(vec![(0.0, 0.0), (1.0, 1.0)]));
+    assert_eq!(function.call(0.25), (2.0*0.25f64.powi(2)).powi(2));
+    assert_eq!(function.call(0.75), (1.0-2.0*0.25f64.powi(2)).powi(2));
+    assert_eq!(function.points().first(), Some(&(0.0, 0.0)));
+    assert_eq!(function.points().last(), Some(&(1.0, 1.0)));
+    assert_eq!(Hedge::Extremely.call(0.5), 0.125);
+    assert_eq!(Hedge::MoreOrLess.call(0.25), 0.5);
+}
+
+#[test]
+fn test_point_validation(
+) {
+    use MembershipFunction::*;
+    assert_eq!(Polyline(vec![(0.0, 0.0), (f64::NAN, 1.0)]).validate(),
+               Err(FuzzyError::NonFinitePoint { x: f64::NAN, y: 1.0 }));
+    assert_eq!(Polyline(vec![(0.0, 0.0), (1.0, f64::INFINITY)]).validate(),
+               Err(FuzzyError::NonFinitePoint { x: 1.0, y: f64::INFINITY }));
+    assert_eq!(Polyline(vec![(0.0, -0.5), (1.0, 1.0)]).validate(),
+               Err(FuzzyError::MembershipOutOfRange { x: 0.0, y: -0.5 }));
+    assert_eq!(Polyline(vec![(0.0, 0.0), (1.0, 1.0), (1.0, 0.5)]).validate(),
+               Err(FuzzyError::ConflictingPoints { x: 1.0, first: 1.0, second: 0.5 }));
+    assert_eq!(Polyline(vec![(1.0, 1.0), (0.0, 0.0), (1.0, 1.0)]).validate(),
+               Ok(Polyline(vec![(0.0, 0.0), (1.0, 1.0)])));
+    assert_eq!(Polyline(vec![(1.0, 1.0), (1.0, 1.0)]).validate(),
+               Err(FuzzyError::InvalidPoints("polyline needs at least 2 distinct points, got 1".to_string())));
+    assert!(matches!(Triangular { a: 0.0, b: f64::NAN, c: 1.0 }.validate(), Err(FuzzyError::InvalidParameters(_))));
+
+    assert_eq!(Polyline(vec![(0.0, -0.5), (1.0, 2.0)]).validate_with(PointPolicy::Clamp),
+               Ok(Polyline(vec![(0.0, 0.0), (1.0, 1.0)])));
+    assert_eq!(Polyline(vec![(0.0, 0.0), (1.0, 4.0), (2.0, 2.0)]).validate_with(PointPolicy::Normalize),
+               Ok(Polyline(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.5)])));
+    assert_eq!(Polyline(vec![(0.0, 0.0), (1.0, 1.0)]).validate_with(PointPolicy::Normalize),
+               Ok(Polyline(vec![(0.0, 0.0), (1.0, 1.0)])));
+
+    assert_eq!(Trapezoidal { a: 0.0, b: 0.0, c: 1.0, d: 1.0 }.points(), vec![(0.0, 1.0), (1.0, 1.0)]);
+}
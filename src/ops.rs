@@ -0,0 +1,313 @@
+use std::fmt;
+use std::sync::Arc;
+
+use super::common::{FuzzyError, FuzzyResult};
+
+/// User supplied binary operator.
+pub type BinaryFn = Arc<dyn Fn(f64, f64) -> f64 + Send + Sync>;
+
+/// Conjunction operator used for `And` rules.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TNorm {
+    /// min(a, b)
+    #[default]
+    Min,
+    /// a*b
+    Product,
+    /// max(0, a+b-1)
+    Lukasiewicz,
+    /// b if a = 1, a if b = 1, 0 otherwise
+    Drastic,
+    /// ab/(2-(a+b-ab))
+    Einstein,
+    /// ab/(p+(1-p)(a+b-ab)), p >= 0
+    Hamacher(f64),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(BinaryFn)
+}
+
+impl TNorm {
+    pub fn call(
+        &self,
+        a: f64,
+        b: f64
+    ) -> f64 {
+        match self {
+            TNorm::Min => a.min(b),
+            TNorm::Product => a*b,
+            TNorm::Lukasiewicz => (a+b-1.0).max(0.0),
+            TNorm::Drastic =>
+                if a == 1.0 { b } else if b == 1.0 { a } else { 0.0 },
+            TNorm::Einstein => a*b/(2.0-(a+b-a*b)),
+            TNorm::Hamacher(p) => {
+                let den = p+(1.0-p)*(a+b-a*b);
+                // Only possible for p = 0 and a = b = 0.
+                if den == 0.0 { 0.0 } else { a*b/den }
+            },
+            TNorm::Custom(f) => f(a, b)
+        }
+    }
+
+    /// Checks parameters, Hamacher needs finite p >= 0.
+    pub fn validate(
+        &self
+    ) -> FuzzyResult<()> {
+        if let TNorm::Hamacher(p) = self {
+            if !(p.is_finite() && *p >= 0.0) {
+                Err(FuzzyError::InvalidParameters(format!("{:?}", self)))?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for TNorm {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        match self {
+            TNorm::Min => write!(f, "Min"),
+            TNorm::Product => write!(f, "Product"),
+            TNorm::Lukasiewicz => write!(f, "Lukasiewicz"),
+            TNorm::Drastic => write!(f, "Drastic"),
+            TNorm::Einstein => write!(f, "Einstein"),
+            TNorm::Hamacher(p) => write!(f, "Hamacher({})", p),
+            TNorm::Custom(_) => write!(f, "Custom")
+        }
+    }
+}
+
+/// Disjunction operator used for `Or` rules.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SNorm {
+    /// max(a, b)
+    #[default]
+    Max,
+    /// a+b-ab
+    ProbabilisticSum,
+    /// min(1, a+b)
+    Lukasiewicz,
+    /// b if a = 0, a if b = 0, 1 otherwise
+    Drastic,
+    /// (a+b)/(1+ab)
+    Einstein,
+    /// (a+b+(p-2)ab)/(1+(p-1)ab), p >= 0
+    Hamacher(f64),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(BinaryFn)
+}
+
+impl SNorm {
+    pub fn call(
+        &self,
+        a: f64,
+        b: f64
+    ) -> f64 {
+        match self {
+            SNorm::Max => a.max(b),
+            SNorm::ProbabilisticSum => a+b-a*b,
+            SNorm::Lukasiewicz => (a+b).min(1.0),
+            SNorm::Drastic =>
+                if a == 0.0 { b } else if b == 0.0 { a } else { 1.0 },
+            SNorm::Einstein => (a+b)/(1.0+a*b),
+            SNorm::Hamacher(p) => {
+                let den = 1.0+(p-1.0)*a*b;
+                // Only possible for p = 0 and a = b = 1.
+                if den == 0.0 { 1.0 } else { (a+b+(p-2.0)*a*b)/den }
+            },
+            SNorm::Custom(f) => f(a, b)
+        }
+    }
+
+    /// Checks parameters, Hamacher needs finite p >= 0.
+    pub fn validate(
+        &self
+    ) -> FuzzyResult<()> {
+        if let SNorm::Hamacher(p) = self {
+            if !(p.is_finite() && *p >= 0.0) {
+                Err(FuzzyError::InvalidParameters(format!("{:?}", self)))?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SNorm {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        match self {
+            SNorm::Max => write!(f, "Max"),
+            SNorm::ProbabilisticSum => write!(f, "ProbabilisticSum"),
+            SNorm::Lukasiewicz => write!(f, "Lukasiewicz"),
+            SNorm::Drastic => write!(f, "Drastic"),
+            SNorm::Einstein => write!(f, "Einstein"),
+            SNorm::Hamacher(p) => write!(f, "Hamacher({})", p),
+            SNorm::Custom(_) => write!(f, "Custom")
+        }
+    }
+}
+
+/// Method of shaping consequent term with rule's firing strength.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Implication {
+    /// Clips term at firing strength (Mamdani).
+    #[default]
+    Min,
+    /// Scales term by firing strength (Larsen).
+    Product,
+    /// Applies t-norm to firing strength and membership in every point.
+    Norm(TNorm)
+}
+
+impl Implication {
+    pub fn call(
+        &self,
+        strength: f64,
+        membership: f64
+    ) -> f64 {
+        match self {
+            Implication::Min => strength.min(membership),
+            Implication::Product => strength*membership,
+            Implication::Norm(norm) => norm.call(strength, membership)
+        }
+    }
+}
+
+/// Method of combining consequents of all fired rules into one output curve.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Aggregation {
+    /// max(a, b)
+    #[default]
+    Max,
+    /// min(1, a+b)
+    BoundedSum,
+    /// a+b, whole curve divided by its height if it exceeds 1.
+    NormalizedSum,
+    /// a+b-ab
+    ProbabilisticOr,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(BinaryFn)
+}
+
+impl Aggregation {
+    /// Combines two memberships. NormalizedSum normalization is applied
+    /// to the whole curve afterwards.
+    pub fn call(
+        &self,
+        a: f64,
+        b: f64
+    ) -> f64 {
+        match self {
+            Aggregation::Max => a.max(b),
+            Aggregation::BoundedSum => (a+b).min(1.0),
+            Aggregation::NormalizedSum => a+b,
+            Aggregation::ProbabilisticOr => a+b-a*b,
+            Aggregation::Custom(f) => f(a, b)
+        }
+    }
+}
+
+impl fmt::Debug for Aggregation {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        match self {
+            Aggregation::Max => write!(f, "Max"),
+            Aggregation::BoundedSum => write!(f, "BoundedSum"),
+            Aggregation::NormalizedSum => write!(f, "NormalizedSum"),
+            Aggregation::ProbabilisticOr => write!(f, "ProbabilisticOr"),
+            Aggregation::Custom(_) => write!(f, "Custom")
+        }
+    }
+}
+
+/// Negation operator used for negated antecedents.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Complement {
+    /// 1-a
+    #[default]
+    Standard,
+    /// Sugeno lambda-complement (1-a)/(1+la), l > -1
+    Sugeno(f64),
+    /// Yager w-complement (1-a^w)^(1/w), w > 0
+    Yager(f64)
+}
+
+impl Complement {
+    pub fn call(
+        &self,
+        a: f64
+    ) -> f64 {
+        match self {
+            Complement::Standard => 1.0-a,
+            Complement::Sugeno(l) => (1.0-a)/(1.0+l*a),
+            Complement::Yager(w) => (1.0-a.powf(*w)).powf(1.0/w)
+        }
+    }
+
+    /// Checks parameters, Sugeno needs finite l > -1 and Yager finite w > 0.
+    pub fn validate(
+        &self
+    ) -> FuzzyResult<()> {
+        let valid = match self {
+            Complement::Standard => true,
+            Complement::Sugeno(l) => l.is_finite() && *l > -1.0,
+            Complement::Yager(w) => w.is_finite() && *w > 0.0
+        };
+        if !valid {
+            Err(FuzzyError::InvalidParameters(format!("{:?}", self)))?
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_norms(
+) {
+    let (a, b) = (0.5, 0.8);
+    assert_eq!(TNorm::Min.call(a, b), 0.5);
+    assert_eq!(TNorm::Product.call(a, b), 0.4);
+    assert!((TNorm::Lukasiewicz.call(a, b)-0.3).abs() < 1e-12);
+    assert_eq!(TNorm::Drastic.call(a, b), 0.0);
+    assert_eq!(TNorm::Drastic.call(1.0, b), 0.8);
+    assert_eq!(TNorm::Einstein.call(a, b), 0.4/1.1);
+    // Hamacher with p = 1 is product.
+    assert_eq!(TNorm::Hamacher(1.0).call(a, b), 0.4);
+    assert_eq!(TNorm::Hamacher(0.0).call(0.0, 0.0), 0.0);
+    assert!((TNorm::Custom(Arc::new(|a, b| a*b*b)).call(a, b)-0.32).abs() < 1e-12);
+
+    assert_eq!(SNorm::Max.call(a, b), 0.8);
+    assert_eq!(SNorm::ProbabilisticSum.call(a, b), 0.9);
+    assert_eq!(SNorm::Lukasiewicz.call(a, b), 1.0);
+    assert_eq!(SNorm::Drastic.call(0.0, b), 0.8);
+    assert_eq!(SNorm::Drastic.call(a, b), 1.0);
+    assert_eq!(SNorm::Einstein.call(a, b), 1.3/1.4);
+    // Hamacher with p = 1 is probabilistic sum.
+    assert_eq!(SNorm::Hamacher(1.0).call(a, b), 0.9);
+    assert_eq!(SNorm::Hamacher(0.0).validate(), Ok(()));
+    assert_eq!(TNorm::Hamacher(-1.0).validate(), Err(FuzzyError::InvalidParameters("Hamacher(-1)".to_string())));
+    assert!(SNorm::Hamacher(f64::NAN).validate().is_err());
+
+    assert_eq!(Complement::Standard.call(0.25), 0.75);
+    assert_eq!(Complement::Sugeno(0.0).call(0.25), 0.75);
+    assert_eq!(Complement::Sugeno(1.0).call(0.25), 0.6);
+    assert_eq!(Complement::Yager(1.0).call(0.25), 0.75);
+    assert_eq!(Complement::Yager(2.0).call(0.6), 0.8);
+    assert_eq!(Complement::Sugeno(-0.5).validate(), Ok(()));
+    assert_eq!(Complement::Sugeno(-1.0).validate(), Err(FuzzyError::InvalidParameters("Sugeno(-1.0)".to_string())));
+    assert_eq!(Complement::Yager(0.0).validate(), Err(FuzzyError::InvalidParameters("Yager(0.0)".to_string())));
+}
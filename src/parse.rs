@@ -0,0 +1,491 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::fuzz::{Antecedent, FuzzyRule};
+use super::membership::{Hedge, HedgedTerm};
+use super::common::{FuzzyError, FuzzyResult, FuzzyIdent};
+
+/// Parses block of rules in the form
+/// `IF loudness IS very quiet AND tod IS morning THEN change IS vol up WITH 0.8`.
+///
+/// Premises support AND, OR (AND binds stronger), NOT and parentheses.
+/// Terms may consist of multiple words and be preceded by hedges, `power 1.5` for
+/// a custom exponent. Terms colliding with keywords or hedges can be quoted,
+/// e.g. `x IS "very high"`, with `\"` and `\\` escaping quotes and backslashes.
+/// Weights have to be in [0, 1].
+/// Rules may span multiple lines, `#` starts a comment.
+pub fn parse_rules(
+    source: &str
+) -> FuzzyResult<Vec<FuzzyRule>> {
+    let mut parser = Parser::new(source)?;
+    let mut rules = Vec::new();
+    while parser.peek().is_some() {
+        rules.push(parser.rule()?);
+    }
+    Ok(rules)
+}
+
+impl FromStr for FuzzyRule {
+    type Err = FuzzyError;
+
+    fn from_str(
+        source: &str
+    ) -> FuzzyResult<Self> {
+        let mut parser = Parser::new(source)?;
+        let rule = parser.rule()?;
+        if parser.peek().is_some() {
+            Err(parser.error("expected end of rule"))?
+        }
+        Ok(rule)
+    }
+}
+
+/// Writes rule in the form accepted by the parser.
+impl fmt::Display for FuzzyRule {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        let (category, term) = &self.consequent;
+        write!(f, "IF {} THEN {} IS ", self.antecedent, quote(category))?;
+        write_term(f, term)?;
+        if self.weight != 1.0 {
+            write!(f, " WITH {}", self.weight)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Antecedent {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter
+    ) -> fmt::Result {
+        // Nested expressions of other type need parentheses, AND binds stronger than OR.
+        let join = |f: &mut fmt::Formatter, items: &[Antecedent], separator: &str| {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", separator)?;
+                }
+                match item {
+                    Antecedent::And(_) | Antecedent::Or(_) => write!(f, "({})", item)?,
+                    _ => write!(f, "{}", item)?
+                }
+            }
+            Ok(())
+        };
+        match self {
+            Antecedent::Is((category, term)) => {
+                write!(f, "{} IS ", quote(category))?;
+                write_term(f, term)
+            },
+            Antecedent::Not(inner) => match inner.as_ref() {
+                Antecedent::Is((category, term)) => {
+                    write!(f, "{} IS NOT ", quote(category))?;
+                    write_term(f, term)
+                },
+                Antecedent::And(_) | Antecedent::Or(_) => write!(f, "NOT ({})", inner),
+                _ => write!(f, "NOT {}", inner)
+            },
+            Antecedent::And(items) => join(f, items, "AND"),
+            Antecedent::Or(items) => join(f, items, "OR")
+        }
+    }
+}
+
+fn write_term(
+    f: &mut fmt::Formatter,
+    term: &HedgedTerm
+) -> fmt::Result {
+    for hedge in term.hedges.iter().rev() {
+        write!(f, "{} ", hedge)?;
+    }
+    write!(f, "{}", quote(&term.term))
+}
+
+/// Quotes names, which are not plain identifiers or collide with keywords and hedges.
+/// Quotes and backslashes inside are escaped with a backslash.
+pub(crate) fn quote(
+    name: &str
+) -> String {
+    let plain = name.chars().next().map(|c| c.is_ascii_alphabetic() || c == '_').unwrap_or(false)
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.iter().chain(HEDGES).any(|k| k.eq_ignore_ascii_case(name));
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Open,
+    Close
+}
+
+/// Token with its line and column, both counted from 1.
+type Spanned = (Token, usize, usize);
+
+fn tokenize(
+    source: &str
+) -> FuzzyResult<Vec<Spanned>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+    while let Some(&c) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let mut advance = |c: char| if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        };
+        match c {
+            '#' => while let Some(&c) = chars.peek() {
+                if c == '\n' {
+                    break;
+                }
+                advance(c);
+                chars.next();
+            },
+            '(' | ')' => {
+                advance(c);
+                chars.next();
+                let token = if c == '(' { Token::Open } else { Token::Close };
+                tokens.push((token, start_line, start_column));
+            },
+            '"' => {
+                advance(c);
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => {
+                            advance('"');
+                            break;
+                        },
+                        Some(c) => {
+                            advance(c);
+                            // Backslash escapes the next character.
+                            let c = match (c, chars.peek()) {
+                                ('\\', Some(&next)) => {
+                                    advance(next);
+                                    chars.next();
+                                    next
+                                },
+                                _ => c
+                            };
+                            text.push(c);
+                        },
+                        None => Err(FuzzyError::Parse {
+                            line: start_line,
+                            column: start_column,
+                            message: "unterminated quote".to_string()
+                        })?
+                    }
+                }
+                tokens.push((Token::Quoted(text), start_line, start_column));
+            },
+            c if c.is_whitespace() => {
+                advance(c);
+                chars.next();
+            },
+            _ => {
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"#".contains(c) {
+                        break;
+                    }
+                    advance(c);
+                    text.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Word(text), start_line, start_column));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+const KEYWORDS: &[&str] = &["IF", "IS", "AND", "OR", "NOT", "THEN", "WITH"];
+const HEDGES: &[&str] = &["very", "extremely", "somewhat", "slightly", "indeed", "more", "power"];
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    position: usize,
+    // Position just past the source, reported for unexpected end of input.
+    end: (usize, usize)
+}
+
+impl Parser {
+    fn new(
+        source: &str
+    ) -> FuzzyResult<Self> {
+        let line = source.lines().count().max(1);
+        let column = source.lines().last().map(|l| l.chars().count()).unwrap_or(0)+1;
+        Ok(Self {
+            tokens: tokenize(source)?,
+            position: 0,
+            end: (line, column)
+        })
+    }
+
+    fn peek(
+        &self
+    ) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _, _)| token)
+    }
+
+    fn peek_word(
+        &self,
+        offset: usize
+    ) -> Option<&str> {
+        match self.tokens.get(self.position+offset) {
+            Some((Token::Word(word), _, _)) => Some(word),
+            _ => None
+        }
+    }
+
+    fn is_keyword(
+        &self,
+        keyword: &str
+    ) -> bool {
+        self.peek_word(0).map(|w| w.eq_ignore_ascii_case(keyword)).unwrap_or(false)
+    }
+
+    fn error(
+        &self,
+        message: &str
+    ) -> FuzzyError {
+        let (line, column) = self.tokens.get(self.position)
+            .map(|(_, line, column)| (*line, *column))
+            .unwrap_or(self.end);
+        let found = match self.peek() {
+            Some(Token::Word(word)) => format!("'{}'", word),
+            Some(Token::Quoted(text)) => format!("\"{}\"", text),
+            Some(Token::Open) => "'('".to_string(),
+            Some(Token::Close) => "')'".to_string(),
+            None => "end of input".to_string()
+        };
+        FuzzyError::Parse { line, column, message: format!("{}, found {}", message, found) }
+    }
+
+    fn expect(
+        &mut self,
+        keyword: &str
+    ) -> FuzzyResult<()> {
+        if !self.is_keyword(keyword) {
+            Err(self.error(&format!("expected {}", keyword)))?
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn rule(
+        &mut self
+    ) -> FuzzyResult<FuzzyRule> {
+        self.expect("IF")?;
+        let antecedent = self.or()?;
+        self.expect("THEN")?;
+        let category = self.words("expected category")?;
+        self.expect("IS")?;
+        if self.is_keyword("NOT") {
+            Err(self.error("consequent can't be negated"))?
+        }
+        let rule = FuzzyRule::new(antecedent, (category, self.term()?));
+        if !self.is_keyword("WITH") {
+            return Ok(rule)
+        }
+        self.position += 1;
+        let weight = self.peek_word(0)
+            .and_then(|word| word.parse::<f64>().ok())
+            .ok_or_else(|| self.error("expected weight"))?;
+        if !(0.0..=1.0).contains(&weight) {
+            Err(self.error("weight out of [0, 1]"))?
+        }
+        self.position += 1;
+        Ok(rule.weight(weight))
+    }
+
+    fn or(
+        &mut self
+    ) -> FuzzyResult<Antecedent> {
+        let mut items = vec![self.and()?];
+        while self.is_keyword("OR") {
+            self.position += 1;
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Antecedent::Or(items) })
+    }
+
+    fn and(
+        &mut self
+    ) -> FuzzyResult<Antecedent> {
+        let mut items = vec![self.unary()?];
+        while self.is_keyword("AND") {
+            self.position += 1;
+            items.push(self.unary()?);
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Antecedent::And(items) })
+    }
+
+    fn unary(
+        &mut self
+    ) -> FuzzyResult<Antecedent> {
+        if self.is_keyword("NOT") {
+            self.position += 1;
+            return Ok(!self.unary()?)
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let antecedent = self.or()?;
+            if self.peek() != Some(&Token::Close) {
+                Err(self.error("expected ')'"))?
+            }
+            self.position += 1;
+            return Ok(antecedent)
+        }
+        let (category, negated, term) = self.clause()?;
+        let antecedent = Antecedent::Is((category, term));
+        Ok(if negated { !antecedent } else { antecedent })
+    }
+
+    /// `category IS [NOT] hedge* term`
+    fn clause(
+        &mut self
+    ) -> FuzzyResult<(String, bool, HedgedTerm)> {
+        let category = self.words("expected category")?;
+        self.expect("IS")?;
+        let negated = self.is_keyword("NOT");
+        if negated {
+            self.position += 1;
+        }
+        Ok((category, negated, self.term()?))
+    }
+
+    /// `hedge* term`
+    fn term(
+        &mut self
+    ) -> FuzzyResult<HedgedTerm> {
+        let mut hedges = Vec::new();
+        while let Some((hedge, length)) = self.hedge() {
+            if let Hedge::Power(p) = hedge {
+                if !(p.is_finite() && p > 0.0) {
+                    self.position += 1;
+                    Err(self.error("expected positive exponent"))?
+                }
+            }
+            hedges.push(hedge);
+            self.position += length;
+        }
+        let mut term = HedgedTerm::from(self.words("expected term")?);
+        // Hedge written closest to the term is applied first.
+        for hedge in hedges.into_iter().rev() {
+            term = term.hedge(hedge);
+        }
+        Ok(term)
+    }
+
+    fn hedge(
+        &self
+    ) -> Option<(Hedge, usize)> {
+        let word = |offset: usize, expected: &str| self.peek_word(offset)
+            .map(|w| w.eq_ignore_ascii_case(expected))
+            .unwrap_or(false);
+        let hedge = match self.peek_word(0)?.to_lowercase().as_str() {
+            "very" => Hedge::Very,
+            "extremely" => Hedge::Extremely,
+            "somewhat" => Hedge::Somewhat,
+            "slightly" => Hedge::Slightly,
+            "indeed" => Hedge::Indeed,
+            "more" if word(1, "or") && word(2, "less") => return Some((Hedge::MoreOrLess, 3)),
+            "power" => return self.peek_word(1)
+                .and_then(|w| w.parse::<f64>().ok())
+                .map(|p| (Hedge::Power(p), 2)),
+            _ => return None
+        };
+        Some((hedge, 1))
+    }
+
+    /// Single quoted name or sequence of words up to the next keyword.
+    fn words(
+        &mut self,
+        message: &str
+    ) -> FuzzyResult<String> {
+        if let Some(Token::Quoted(text)) = self.peek() {
+            let text = text.clone();
+            self.position += 1;
+            return Ok(text)
+        }
+        let mut words: Vec<String> = Vec::new();
+        while let Some(word) = self.peek_word(0) {
+            if KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word)) {
+                break;
+            }
+            words.push(word.to_string());
+            self.position += 1;
+        }
+        if words.is_empty() {
+            Err(self.error(message))?
+        }
+        Ok(words.join(" "))
+    }
+}
+
+#[test]
+fn test_parse(
+) -> FuzzyResult<()> {
+    use super::membership::{very, more_or_less};
+    let ident = |c: &str, t: HedgedTerm| -> FuzzyIdent { (c.to_string(), t) };
+    let is = |c: &str, t: HedgedTerm| Antecedent::Is(ident(c, t));
+
+    let rule: FuzzyRule = "IF loudness IS very quiet AND tod IS morning THEN change IS vol up WITH 0.8".parse()?;
+    assert_eq!(rule, FuzzyRule::new(
+        Antecedent::And(vec![is("loudness", very("quiet")), is("tod", "morning".into())]),
+        ident("change", "vol up".into())
+    ).weight(0.8));
+
+    let rule: FuzzyRule = "if a is x or not (b is not more or less y and c is \"very z\") then d is w".parse()?;
+    assert_eq!(rule, FuzzyRule::new(
+        Antecedent::Or(vec![
+            is("a", "x".into()),
+            !Antecedent::And(vec![!is("b", more_or_less("y")), is("c", "very z".into())])
+        ]),
+        ident("d", "w".into())
+    ));
+
+    let rules = parse_rules("# Comment\nIF a IS x THEN d IS w\nIF a IS y\n  THEN d IS v WITH 0.5\n")?;
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[1].consequent(), &ident("d", "v".into()));
+
+    let error = |line, column, message: &str| FuzzyError::Parse { line, column, message: message.to_string() };
+    assert_eq!("IF a IS x\nTHEN d w".parse::<FuzzyRule>(),
+               Err(error(2, 9, "expected IS, found end of input")));
+    assert_eq!("IF a IS x AND (b IS y THEN d IS w".parse::<FuzzyRule>(),
+               Err(error(1, 23, "expected ')', found 'THEN'")));
+    assert_eq!("IF a IS THEN d IS w".parse::<FuzzyRule>(),
+               Err(error(1, 9, "expected term, found 'THEN'")));
+    assert_eq!("IF a IS x THEN d IS w WITH high".parse::<FuzzyRule>(),
+               Err(error(1, 28, "expected weight, found 'high'")));
+    assert_eq!("IF a IS x THEN d IS NOT w".parse::<FuzzyRule>(),
+               Err(error(1, 21, "consequent can't be negated, found 'NOT'")));
+    assert_eq!("IF a IS \"x".parse::<FuzzyRule>(), Err(error(1, 9, "unterminated quote")));
+    assert_eq!("IF a IS x THEN d IS w WITH NaN".parse::<FuzzyRule>(),
+               Err(error(1, 28, "weight out of [0, 1], found 'NaN'")));
+    assert_eq!("IF a IS x THEN d IS w WITH 1.5".parse::<FuzzyRule>(),
+               Err(error(1, 28, "weight out of [0, 1], found '1.5'")));
+    assert!("IF a IS x THEN d IS w WITH inf".parse::<FuzzyRule>().is_err());
+    assert_eq!("IF a IS power -2 x THEN d IS w".parse::<FuzzyRule>(),
+               Err(error(1, 15, "expected positive exponent, found '-2'")));
+    let rule: FuzzyRule = "IF \"say \\\"hi\\\"\" IS x THEN d IS w".parse()?;
+    assert_eq!(rule.antecedent(), &Antecedent::Is(ident("say \"hi\"", "x".into())));
+
+    let text = "IF (a IS x OR NOT (b IS y AND c IS NOT very \"z z\")) AND d IS \"more\" THEN e IS more or less w WITH 0.5";
+    let rule: FuzzyRule = text.parse()?;
+    assert_eq!(rule.to_string(), text);
+    assert_eq!(rule.to_string().parse::<FuzzyRule>()?, rule);
+    Ok(())
+}
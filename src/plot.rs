@@ -12,7 +12,7 @@ use super::common::{
     FuzzyResult
 };
 
-static COLORS: &'static [&'static str] = &[
+static COLORS: &[&str] = &[
     "#ffbe0b",
     "#fb5607",
     "#ff006e",
@@ -62,11 +62,18 @@ pub fn set(
         .y_range(0.0, 1.0)
         .x_label(x_label)
         .y_label(y_label);
+    // Periodic terms are drawn wrapped into a single period, bounded ones over the universe.
+    if let Some(period) = set.period() {
+        view = view.x_range(0.0, period);
+    }
+    if let Some((min, max)) = set.bounds() {
+        view = view.x_range(min, max);
+    }
 
-    for (i, (_, points)) in set.terms().enumerate() {
+    for (i, (_, function)) in set.terms().enumerate() {
         let color = COLORS[i%COLORS.len()];
         let style = LineStyle::new().colour(color);
-        let plot = Plot::new(points.to_vec()).line_style(style);
+        let plot = Plot::new(set.curve(function)).line_style(style);
         view = view.add(plot);
     }
 
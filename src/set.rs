@@ -1,47 +1,302 @@
 use std::collections::HashMap;
 
+use super::membership::{combine, MembershipFunction, HedgedTerm, PointPolicy, SAMPLES};
+use super::ops::{Implication, TNorm, SNorm, Complement};
 use super::common::{FuzzyError, FuzzyResult, Term};
 
-#[derive(Debug, Clone, PartialEq)]
+/// What membership of x outside of the universe of a bounded set is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RangePolicy {
+    /// Membership at the nearest bound.
+    #[default]
+    Clamp,
+    /// Function evaluated as if there were no bounds.
+    Extrapolate,
+    /// Fail with `FuzzyError::OutOfRange`.
+    Error,
+    /// No membership in any term.
+    Zero
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct FuzzySet {
-    terms: HashMap<Term, Vec<(f64, f64)>>
+    terms: HashMap<Term, MembershipFunction>,
+    /// Length of a periodic universe [0, period), if any.
+    pub(crate) period: Option<f64>,
+    /// Bounds of the universe [min, max], if any.
+    pub(crate) universe: Option<(f64, f64)>,
+    pub(crate) out_of_range: RangePolicy
+}
+
+/// Serialized as `{ "period": ..., "universe": [min, max], "out_of_range": ..., "terms": { ... } }`,
+/// where terms map names to functions, sorted by name, and other keys are present
+/// only for periodic and bounded sets.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FuzzySet {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        struct Terms<'a>(&'a HashMap<Term, MembershipFunction>);
+        impl serde::Serialize for Terms<'_> {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S
+            ) -> Result<S::Ok, S::Error> {
+                super::common::serialize_sorted(self.0, serializer)
+            }
+        }
+        let fields = 1+self.period.iter().count()+2*self.universe.iter().count();
+        let mut state = serializer.serialize_struct("FuzzySet", fields)?;
+        if let Some(period) = self.period {
+            state.serialize_field("period", &period)?;
+        }
+        if let Some(universe) = self.universe {
+            state.serialize_field("universe", &universe)?;
+            state.serialize_field("out_of_range", &self.out_of_range)?;
+        }
+        state.serialize_field("terms", &Terms(&self.terms))?;
+        state.end()
+    }
+}
+
+/// Functions are validated like in `FuzzySet::term`.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FuzzySet {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Repr {
+            #[serde(default)]
+            period: Option<f64>,
+            #[serde(default)]
+            universe: Option<(f64, f64)>,
+            #[serde(default)]
+            out_of_range: RangePolicy,
+            terms: HashMap<Term, MembershipFunction>
+        }
+        let repr = Repr::deserialize(deserializer)?;
+        let mut set = FuzzySet::new();
+        if let Some(period) = repr.period {
+            set = set.periodic(period).map_err(serde::de::Error::custom)?;
+        }
+        if let Some((min, max)) = repr.universe {
+            set = set.universe(min, max).map_err(serde::de::Error::custom)?;
+        }
+        let set = set.out_of_range(repr.out_of_range);
+        repr.terms.into_iter()
+            .try_fold(set, |set, (term, function)| set.term(term, function))
+            .map_err(serde::de::Error::custom)
+    }
 }
 
 impl FuzzySet {
     pub fn new(
     ) -> Self {
         Self {
-            terms: HashMap::new()
+            terms: HashMap::new(),
+            period: None,
+            universe: None,
+            out_of_range: RangePolicy::Clamp
+        }
+    }
+
+    /// Makes universe of the set periodic, e.g. 24 hours or 360 degrees.
+    /// Terms wrap across the boundary: x and x+period have the same memberships,
+    /// and each term is treated as a closed curve. Terms should span at most one period.
+    pub fn periodic(
+        mut self,
+        period: f64
+    ) -> FuzzyResult<Self> {
+        if !(period.is_finite() && period > 0.0) {
+            Err(FuzzyError::InvalidParameters(format!("period must be positive, got {}", period)))?
         }
+        if self.universe.is_some() {
+            Err(FuzzyError::InvalidParameters("bounded set can't be periodic".to_string()))?
+        }
+        self.period = Some(period);
+        Ok(self)
     }
 
+    /// Bounds universe of the set to [min, max]. Terms are cut to the universe,
+    /// so that defuzzification integrates over it, and memberships of x outside
+    /// of it follow the policy set with `out_of_range` (clamping by default).
+    pub fn universe(
+        mut self,
+        min: f64,
+        max: f64
+    ) -> FuzzyResult<Self> {
+        if !(min.is_finite() && max.is_finite() && min < max) {
+            Err(FuzzyError::InvalidParameters(format!("invalid universe [{}, {}]", min, max)))?
+        }
+        if self.period.is_some() {
+            Err(FuzzyError::InvalidParameters("periodic set can't be bounded".to_string()))?
+        }
+        self.universe = Some((min, max));
+        Ok(self)
+    }
+
+    /// Sets how memberships of x outside of the universe are calculated.
+    pub fn out_of_range(
+        mut self,
+        policy: RangePolicy
+    ) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// Set without terms over the same universe.
+    pub(crate) fn empty_like(
+        &self
+    ) -> Self {
+        Self {
+            terms: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Bounds of the universe, if the set is bounded.
+    pub fn bounds(
+        &self
+    ) -> Option<(f64, f64)> {
+        self.universe
+    }
+
+    /// Length of the periodic universe, if the set is periodic.
+    pub fn period(
+        &self
+    ) -> Option<f64> {
+        self.period
+    }
+
+    /// Adds new term. Accepts points of a polyline or any MembershipFunction.
     pub fn term(
+        self,
+        key: impl Into<Term>,
+        function: impl Into<MembershipFunction>
+    ) -> FuzzyResult<Self> {
+        self.term_with(key, function, PointPolicy::Strict)
+    }
+
+    /// Adds term, handling memberships outside of [0, 1] according to policy.
+    pub fn term_with(
         mut self,
         key: impl Into<Term>,
-        mut points: Vec<(f64, f64)>
+        function: impl Into<MembershipFunction>,
+        policy: PointPolicy
     ) -> FuzzyResult<Self> {
-        if points.len() < 2 {
-            Err(FuzzyError::InvalidPoints)?
-        }
-        // Order points by x axis.
-        points.sort_by(|(x1, _), (x2, _)| x1.partial_cmp(x2).unwrap());
-        self.terms.insert(key.into(), points);
+        let key = key.into();
+        let function = function.into().validate_with(policy).map_err(|error| error.in_term(key.clone()))?;
+        self.terms.insert(key, function);
         Ok(self)
     }
 
     pub fn terms(
         &self
-    ) -> impl Iterator<Item=(&Term, &Vec<(f64, f64)>)> {
+    ) -> impl Iterator<Item=(&Term, &MembershipFunction)> {
         self.terms.iter()
     }
 
-    pub fn points(
+    pub fn function(
         &self,
         term: impl Into<Term>
-    ) -> FuzzyResult<&Vec<(f64, f64)>> {
+    ) -> FuzzyResult<&MembershipFunction> {
         let key = term.into();
         self.terms.get(&key)
-            .ok_or(FuzzyError::InvalidTerm(key.into()))
+            .ok_or(FuzzyError::InvalidTerm(key))
+    }
+
+    /// Function of the term with hedges applied.
+    pub fn resolve(
+        &self,
+        term: &HedgedTerm
+    ) -> FuzzyResult<MembershipFunction> {
+        self.function(term.term.clone())
+            .map(|function| term.apply(function.clone()))
+    }
+
+    /// Piecewise-linear representation of the term.
+    /// For periodic sets it is the closed curve over [0, period].
+    pub fn points(
+        &self,
+        term: impl Into<Term>
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        self.function(term).map(|function| self.curve(function))
+    }
+
+    /// Points of the function, wrapped into [0, period] for periodic sets
+    /// and cut to [min, max] for bounded ones.
+    pub(crate) fn curve(
+        &self,
+        function: &MembershipFunction
+    ) -> Vec<(f64, f64)> {
+        let points = function.points();
+        if let Some((min, max)) = self.universe {
+            let mut bounded = vec![(min, function.call(min))];
+            bounded.extend(points.into_iter().filter(|(x, _)| *x > min && *x < max));
+            bounded.push((max, function.call(max)));
+            return bounded
+        }
+        let period = match self.period {
+            Some(period) => period,
+            None => return points
+        };
+        let mut points = points.into_iter()
+            .map(|(x, y)| {
+                // Rounding may push tiny negative values to period itself.
+                let x = x.rem_euclid(period);
+                (if x >= period { 0.0 } else { x }, y)
+            })
+            .collect::<Vec<(f64, f64)>>();
+        points.sort_by(|(x1, _), (x2, _)| x1.total_cmp(x2));
+        // Parts of the curve meeting at the same x after wrapping, keep higher membership.
+        points.dedup_by(|(x2, y2), (x1, y1)| {
+            if x1 == x2 {
+                *y1 = y1.max(*y2);
+            }
+            x1 == x2
+        });
+        let (first, last) = (points[0], points[points.len()-1]);
+        // Membership at the boundary, on the segment closing the curve.
+        let y = if first.0 == 0.0 || first.0 == last.0 {
+            first.1
+        } else {
+            let x1 = last.0-period;
+            last.1+(first.1-last.1)*(0.0-x1)/(first.0-x1)
+        };
+        if first.0 > 0.0 {
+            points.insert(0, (0.0, y));
+        }
+        points.push((period, y));
+        points
+    }
+
+    /// Membership of x in function, x wrapped around for periodic sets
+    /// and handled according to `RangePolicy` outside of bounded ones.
+    fn evaluate(
+        &self,
+        function: &MembershipFunction,
+        x: f64
+    ) -> FuzzyResult<f64> {
+        if let Some((min, max)) = self.universe {
+            if x < min || x > max {
+                return match self.out_of_range {
+                    RangePolicy::Clamp => Ok(function.call(x.clamp(min, max))),
+                    RangePolicy::Extrapolate => Ok(function.call(x)),
+                    RangePolicy::Error => Err(FuzzyError::OutOfRange { x, min, max }),
+                    RangePolicy::Zero => Ok(0.0)
+                }
+            }
+        }
+        Ok(match self.period {
+            Some(period) => wrapped(function, period, x),
+            None => function.call(x)
+        })
     }
 
     /// Aplies maximum threshold for given term.
@@ -51,8 +306,8 @@ impl FuzzySet {
         value: f64
     ) -> FuzzyResult<()> {
         let key = term.into();
-        let mut points = self.terms.remove(&key)
-            .ok_or(FuzzyError::InvalidTerm(key.clone()))?;
+        let mut points = self.points(key.clone())?;
+        self.terms.remove(&key);
 
         // Three cases:
         // 1. Threshold above maximum y -> Do nothing
@@ -69,8 +324,7 @@ impl FuzzySet {
 
         let find_x = |i: usize, y: f64| -> FuzzyResult<f64> {
             let p1 = points_copy.get(i).unwrap();
-            let p2 = points_copy.get(i+1).unwrap_or_else(|| p1);
-            println!("Y: {} | P1: {:?} | P2: {:?}", y, p1, p2);
+            let p2 = points_copy.get(i+1).unwrap_or(p1);
             let (x1, y1) = p1;
             let (x2, y2) = p2;
             // Check if valid range.
@@ -86,7 +340,7 @@ impl FuzzySet {
 
         // Do actual replacement.
         let mut replace_interval = |from: i32, to: i32| -> FuzzyResult<()> {
-            println!("REPLACE: {}/{}", from, to);
+            tracing::trace!(from, to, threshold = value, "replacing interval above threshold");
             match (from, to) {
                 (-1, -1) => {},
                 // Whole set above threshold -> Replace whole set with two points.
@@ -97,20 +351,23 @@ impl FuzzySet {
                     points.push((x1, value));
                     points.push((x2, value));
                 },
-                // Right side of the set above threshold -> Replace with one point.
+                // Right side of the set above threshold -> Replace with plateau up to the last point.
                 (i, j) if j as usize == points_copy.len()-1 => {
                     let i = i as usize;
-                    let j = j as usize;
                     let x1 = find_x(i-1, value)?;
-                    points.drain(j..points_copy.len());
+                    let (x2, _) = *points.last().unwrap();
+                    points.drain(i..points_copy.len());
                     points.push((x1, value));
+                    points.push((x2, value));
                 },
-                // Left side of the set above threshold -> Replace with one point
+                // Left side of the set above threshold -> Replace with plateau from the first point.
                 (0, j) | (-1, j) => {
                     let j = j as usize;
                     let x1 = find_x(j, value)?;
+                    let (x0, _) = *points.first().unwrap();
                     points.drain(0..j+1);
                     points.insert(0, (x1, value));
+                    points.insert(0, (x0, value));
                 },
                 // Middle of set above threshold -> Replace interval with two points.
                 (i, j) => {
@@ -128,21 +385,18 @@ impl FuzzySet {
         // Pointers to interval positions.
         let (mut int_start, mut int_end) = (-1, -1);
         // Start from the end to prevent indexing issues after element removal.
-        for (i, (_x, y)) in points_copy.iter().enumerate().rev() {
-            println!("{}/({}, {})", i, _x, y);
+        for (i, (_, y)) in points_copy.iter().enumerate().rev() {
             match (int_start, int_end) {
                 // No interval processed.
                 (-1, -1) => {
                     if *y >= value {
                         int_end = i as i32;
-                        println!("HERE 1 {} -> {}", i, int_end);
                     }
                 },
                 // Start of the interval
                 (-1, _) => {
                     if *y < value {
                         int_start = i as i32+1;
-                        println!("HERE 2 {} -> ({}, {})", i, int_start, int_end);
                         replace_interval(int_start, int_end)?;
                         int_start = -1;
                         int_end = -1;
@@ -153,41 +407,290 @@ impl FuzzySet {
             }
         }
         replace_interval(int_start, int_end)?;
+        // Plateau ends coincide with boundary points cut exactly at the threshold.
+        points.dedup();
+
+        self.terms.insert(key, MembershipFunction::Polyline(points));
+        Ok(())
+    }
 
-        self.terms.insert(key, points);
+    /// Scales membership of given term by value.
+    pub fn apply_scale(
+        &mut self,
+        term: impl Into<Term>,
+        value: f64
+    ) -> FuzzyResult<()> {
+        let key = term.into();
+        let points = self.points(key.clone())?
+            .into_iter()
+            .map(|(x, y)| (x, y*value))
+            .collect();
+        self.terms.insert(key, MembershipFunction::Polyline(points));
         Ok(())
     }
 
+    /// Shapes given term with firing strength using implication method.
+    /// MIN and PRODUCT are exact, other methods are sampled.
+    pub fn apply_implication(
+        &mut self,
+        term: impl Into<Term>,
+        value: f64,
+        implication: &Implication
+    ) -> FuzzyResult<()> {
+        let key = term.into();
+        match implication {
+            Implication::Min => self.apply_threshold(key, value),
+            Implication::Product => self.apply_scale(key, value),
+            Implication::Norm(_) => {
+                let points = sampled(&self.points(key.clone())?)
+                    .into_iter()
+                    .map(|(x, y)| (x, implication.call(value, y)))
+                    .collect();
+                self.terms.insert(key, MembershipFunction::Polyline(points));
+                Ok(())
+            }
+        }
+    }
+
+    /// Intersection of a term with a term of other set (possibly this one) as a new polyline.
+    /// Crossing points are inserted, so the result is exact for Min and Lukasiewicz,
+    /// other t-norms are sampled.
+    pub fn intersection(
+        &self,
+        term: impl Into<HedgedTerm>,
+        other: &FuzzySet,
+        other_term: impl Into<HedgedTerm>,
+        norm: &TNorm
+    ) -> FuzzyResult<MembershipFunction> {
+        let exact = matches!(norm, TNorm::Min | TNorm::Lukasiewicz);
+        self.algebra(term.into(), other, other_term.into(), exact, |a, b| norm.call(a, b))
+    }
+
+    /// Union of a term with a term of other set (possibly this one) as a new polyline.
+    /// Crossing points are inserted, so the result is exact for Max and Lukasiewicz,
+    /// other s-norms are sampled.
+    pub fn union(
+        &self,
+        term: impl Into<HedgedTerm>,
+        other: &FuzzySet,
+        other_term: impl Into<HedgedTerm>,
+        norm: &SNorm
+    ) -> FuzzyResult<MembershipFunction> {
+        let exact = matches!(norm, SNorm::Max | SNorm::Lukasiewicz);
+        self.algebra(term.into(), other, other_term.into(), exact, |a, b| norm.call(a, b))
+    }
+
+    /// Complement of a term as a new polyline. Exact for Standard complement, others are sampled.
+    pub fn complement(
+        &self,
+        term: impl Into<HedgedTerm>,
+        complement: &Complement
+    ) -> FuzzyResult<MembershipFunction> {
+        let mut points = self.curve(&self.resolve(&term.into())?);
+        if !matches!(complement, Complement::Standard) {
+            points = sampled(&points);
+        }
+        points.iter_mut().for_each(|(_, y)| *y = complement.call(*y));
+        MembershipFunction::Polyline(points).validate()
+    }
+
+    /// Combines two terms point by point.
+    fn algebra(
+        &self,
+        term: HedgedTerm,
+        other: &FuzzySet,
+        other_term: HedgedTerm,
+        exact: bool,
+        op: impl Fn(f64, f64) -> f64
+    ) -> FuzzyResult<MembershipFunction> {
+        let mut a = self.curve(&self.resolve(&term)?);
+        let mut b = other.curve(&other.resolve(&other_term)?);
+        if !exact {
+            a = sampled(&a);
+            b = sampled(&b);
+        }
+        MembershipFunction::Polyline(combine(&a, &b, op)).validate()
+    }
+
+    /// Intervals where membership of the term is above 0.
+    /// Measures are exact for polylines and taken over the points of the term:
+    /// the universe for bounded sets, [0, period] for periodic ones.
+    pub fn support(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        Ok(cut(&self.measured(term)?, 0.0, true))
+    }
+
+    /// Intervals where membership of the term is 1.
+    pub fn core(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        Ok(cut(&self.measured(term)?, 1.0, false))
+    }
+
+    /// Intervals where membership of the term is at least alpha.
+    pub fn alpha_cut(
+        &self,
+        term: impl Into<HedgedTerm>,
+        alpha: f64
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        if !(0.0..=1.0).contains(&alpha) {
+            Err(FuzzyError::InvalidParameters(format!("alpha {} out of [0, 1]", alpha)))?
+        }
+        Ok(cut(&self.measured(term)?, alpha, false))
+    }
+
+    /// Highest membership of the term.
+    pub fn height(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<f64> {
+        Ok(self.measured(term)?.iter().fold(0.0, |acc: f64, (_, y)| acc.max(*y)))
+    }
+
+    /// Intervals where membership of the term is 0.5, degenerated to a point
+    /// unless the term is flat at 0.5.
+    pub fn crossover(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        let points = self.measured(term)?;
+        let mut intervals: Vec<(f64, f64)> = Vec::new();
+        for (i, &(x, y)) in points.iter().enumerate() {
+            if y == 0.5 {
+                match intervals.last_mut() {
+                    // Flat segment at 0.5 extends the previous interval.
+                    Some((_, end)) if i > 0 && points[i-1].1 == 0.5 => *end = x,
+                    _ => intervals.push((x, x))
+                }
+            } else if let Some(&(x2, y2)) = points.get(i+1) {
+                if (y-0.5)*(y2-0.5) < 0.0 {
+                    let x = x+(x2-x)*(0.5-y)/(y2-y);
+                    intervals.push((x, x));
+                }
+            }
+        }
+        Ok(intervals)
+    }
+
+    /// Scalar cardinality, the area under the term.
+    /// Infinite for shoulders of unbounded sets.
+    pub fn cardinality(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<f64> {
+        Ok(self.measured(term)?
+            .windows(2)
+            .map(|w| {
+                let height = (w[0].1+w[1].1)/2.0;
+                // Zero membership over an infinite width has no area.
+                if height == 0.0 { 0.0 } else { height*(w[1].0-w[0].0) }
+            })
+            .sum())
+    }
+
+    /// Cardinality divided by width of the universe or the period.
+    /// Fails for sets which are neither bounded nor periodic.
+    pub fn relative_cardinality(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<f64> {
+        let width = match (self.universe, self.period) {
+            (Some((min, max)), _) => max-min,
+            (None, Some(period)) => period,
+            (None, None) => Err(FuzzyError::InvalidParameters(
+                "relative cardinality needs a bounded or periodic universe".to_string()))?
+        };
+        Ok(self.cardinality(term)?/width)
+    }
+
+    /// Points of the term, hedges applied, over which measures are taken.
+    /// Terms of unbounded sets are extended to infinity with their limits,
+    /// stepping there from the outermost points of sampled functions.
+    fn measured(
+        &self,
+        term: impl Into<HedgedTerm>
+    ) -> FuzzyResult<Vec<(f64, f64)>> {
+        let function = self.resolve(&term.into())?;
+        let points = self.curve(&function);
+        if self.universe.is_some() || self.period.is_some() {
+            return Ok(points)
+        }
+        let (low, high) = function.limits();
+        let (first, last) = (points[0], points[points.len()-1]);
+        let mut extended = vec![(f64::NEG_INFINITY, low)];
+        if low != first.1 {
+            extended.push((first.0, low));
+        }
+        extended.extend(points);
+        if high != last.1 {
+            extended.push((last.0, high));
+        }
+        extended.push((f64::INFINITY, high));
+        Ok(extended)
+    }
+
+    /// Checks whether membership of the term is non-decreasing or non-increasing.
+    pub fn is_monotonic(
+        &self,
+        term: impl Into<Term>
+    ) -> FuzzyResult<bool> {
+        let points = self.points(term)?;
+        let increasing = points.windows(2).all(|w| w[0].1 <= w[1].1);
+        let decreasing = points.windows(2).all(|w| w[0].1 >= w[1].1);
+        Ok(increasing || decreasing)
+    }
+
+    /// Finds x for which monotonic term reaches membership y.
+    /// For non-decreasing terms it is the smallest such x, for non-increasing the largest one.
+    /// If y is never reached, x of the highest membership is returned.
+    pub fn invert(
+        &self,
+        term: impl Into<Term>,
+        y: f64
+    ) -> FuzzyResult<f64> {
+        let key = term.into();
+        if !self.is_monotonic(key.clone())? {
+            Err(FuzzyError::NonMonotonicTerm(key.clone()))?
+        }
+        let mut points = self.points(key)?;
+        // Walk non-increasing terms from the right.
+        if points.first().unwrap().1 > points.last().unwrap().1 {
+            points.reverse();
+        }
+        for pair in points.windows(2) {
+            let (x1, y1) = pair[0];
+            let (x2, y2) = pair[1];
+            if y1 >= y {
+                return Ok(x1)
+            }
+            if y2 >= y {
+                return Ok(x1+(x2-x1)*(y-y1)/(y2-y1))
+            }
+        }
+        Ok(points.last().unwrap().0)
+    }
+
     pub fn call_single(
         &self,
         term: impl Into<String>,
         x: f64
     ) -> FuzzyResult<f64> {
         let key = term.into();
-        let values = self.terms.get(&key)
-            .ok_or(FuzzyError::InvalidTerm(key))?;
-
-        let values = &values[..];
-        let (mut lx, mut ly) = values.first().unwrap();
-        let (mut rx, mut ry) = values.last().unwrap();
-        if x < lx {
-            rx = lx; ry = ly;
-        } else if x > rx {
-            lx = rx; ly = ry;
-        } else {
-            for window in values.windows(2) {
-                let (px, py) = window[0];
-                let (cx, cy) = window[1];
-                if px <= x && cx >= x {
-                    lx = px; ly = py;
-                    rx = cx; ry = cy;
-                    break;
-                }
-            }
-        }
-        // Linear interpolation to find y.
-        let slope = if lx == rx  { 0.0 } else { (ly-ry)/(lx-rx) };
-        Ok(ly+(x-lx)*slope)
+        self.terms.get(&key)
+            .ok_or(FuzzyError::InvalidTerm(key))
+            .and_then(|function| self.evaluate(function, x))
+    }
+
+    /// Membership of x in the term with hedges applied.
+    pub fn call_hedged(
+        &self,
+        term: &HedgedTerm,
+        x: f64
+    ) -> FuzzyResult<f64> {
+        self.resolve(term).and_then(|function| self.evaluate(&function, x))
     }
 
     pub fn call(
@@ -204,6 +707,72 @@ impl FuzzySet {
 }
 
 
+/// Membership of x on the closed curve of function, see `FuzzySet::curve`: the higher of
+/// copies of the function shifted by whole periods within its range and of the segment
+/// closing the curve from its end to its start one period later.
+fn wrapped(
+    function: &MembershipFunction,
+    period: f64,
+    x: f64
+) -> f64 {
+    let (low, high) = function.range();
+    let mut y: f64 = 0.0;
+    let mut shifted = low+(x-low).rem_euclid(period);
+    while shifted <= high {
+        y = y.max(function.call(shifted));
+        shifted += period;
+    }
+    let gap = low+period-high;
+    let offset = (x-high).rem_euclid(period);
+    if offset < gap {
+        let (end, start) = (function.call(high), function.call(low));
+        y = y.max(end+(start-end)*offset/gap);
+    }
+    y
+}
+
+/// Intervals of polyline where membership is at least level, or above it if strict.
+/// Touching intervals are merged.
+fn cut(
+    points: &[(f64, f64)],
+    level: f64,
+    strict: bool
+) -> Vec<(f64, f64)> {
+    let inside = |y: f64| if strict { y > level } else { y >= level };
+    let mut intervals: Vec<(f64, f64)> = Vec::new();
+    let mut push = |from: f64, to: f64| match intervals.last_mut() {
+        Some((_, end)) if *end == from => *end = to,
+        _ => intervals.push((from, to))
+    };
+    for pair in points.windows(2) {
+        let (x1, y1) = pair[0];
+        let (x2, y2) = pair[1];
+        let crossing = || x1+(x2-x1)*(level-y1)/(y2-y1);
+        match (inside(y1), inside(y2)) {
+            (true, true) => push(x1, x2),
+            (true, false) => push(x1, crossing()),
+            (false, true) => push(crossing(), x2),
+            (false, false) => {}
+        }
+    }
+    intervals
+}
+
+/// Polyline with evenly spaced samples added between its ends, vertices are kept.
+fn sampled(
+    points: &[(f64, f64)]
+) -> Vec<(f64, f64)> {
+    let (from, to) = (points.first().unwrap().0, points.last().unwrap().0);
+    let step = (to-from)/(SAMPLES-1) as f64;
+    let mut xs = points.iter()
+        .map(|(x, _)| *x)
+        .chain((1..SAMPLES-1).map(|i| from+step*i as f64))
+        .collect::<Vec<f64>>();
+    xs.sort_by(|a, b| a.total_cmp(b));
+    xs.dedup();
+    xs.into_iter().map(|x| (x, MembershipFunction::interpolate(points, x))).collect()
+}
+
 #[macro_export]
 macro_rules! fuzzy {
     ($($term:expr => $(($x:expr,$y:expr)),* $(,)*);* $(;)*) => {{
@@ -214,13 +783,213 @@ macro_rules! fuzzy {
 
 #[test]
 fn test_fuzzy_macro(
-) -> () {
+) {
     assert_eq!(fuzzy!{
-        "term1" => (1.0, 1.0), (2.0, 2.0);
+        "term1" => (1.0, 1.0), (2.0, 0.0);
         "term2" => (0.0, 0.5)
-    }, Err(FuzzyError::InvalidPoints));
+    }, Err(FuzzyError::InvalidPoints("polyline needs at least 2 points, got 1".to_string()).in_term("term2")));
     assert_eq!(fuzzy!{
-        "term1" => (1.0, 1.0), (0.0, 2.0)
-    }, FuzzySet::new().term("term1", vec![(0.0, 2.0), (1.0, 1.0)]));
+        "term1" => (1.0, 1.0), (0.0, 0.5)
+    }, FuzzySet::new().term("term1", vec![(0.0, 0.5), (1.0, 1.0)]));
     assert_eq!(fuzzy!{}, Ok(FuzzySet::new()))
 }
+
+#[test]
+fn test_term_policy(
+) -> FuzzyResult<()> {
+    assert_eq!(FuzzySet::new().term("loud", vec![(0.0, 0.0), (1.0, 1.5)]),
+               Err(FuzzyError::MembershipOutOfRange { x: 1.0, y: 1.5 }.in_term("loud")));
+    let set = FuzzySet::new().term_with("loud", vec![(0.0, 0.0), (1.0, 1.5)], PointPolicy::Clamp)?;
+    assert_eq!(set.call_single("loud", 1.0)?, 1.0);
+    Ok(())
+}
+
+#[test]
+fn test_function_terms(
+) -> FuzzyResult<()> {
+    let mut set = FuzzySet::new()
+        .term("low", MembershipFunction::Triangular { a: 0.0, b: 2.0, c: 4.0 })?
+        .term("mid", MembershipFunction::Gaussian { mean: 5.0, sigma: 1.0 })?;
+
+    assert_eq!(set.call_single("low", 1.0)?, 0.5);
+    assert_eq!(set.call_single("mid", 5.0)?, 1.0);
+    assert_eq!(set.points("low")?, vec![(0.0, 0.0), (2.0, 1.0), (4.0, 0.0)]);
+
+    set.apply_threshold("low", 0.5)?;
+    assert_eq!(set.points("low")?, vec![(0.0, 0.0), (1.0, 0.5), (3.0, 0.5), (4.0, 0.0)]);
+    // Shoulders keep their plateau up to the outermost point.
+    let mut shoulders = FuzzySet::new()
+        .term("low", vec![(0.0, 1.0), (2.0, 1.0), (4.0, 0.0)])?
+        .term("high", vec![(0.0, 0.0), (2.0, 1.0), (4.0, 1.0)])?;
+    shoulders.apply_threshold("low", 0.5)?;
+    shoulders.apply_threshold("high", 0.5)?;
+    assert_eq!(shoulders.points("low")?, vec![(0.0, 0.5), (3.0, 0.5), (4.0, 0.0)]);
+    assert_eq!(shoulders.points("high")?, vec![(0.0, 0.0), (1.0, 0.5), (4.0, 0.5)]);
+    set.apply_threshold("mid", 0.5)?;
+    assert!(set.points("mid")?.iter().all(|(_, y)| *y <= 0.5));
+    assert!((super::defuzz::cog(set.points("mid")?)?-5.0).abs() < 1e-9);
+    Ok(())
+}
+
+#[test]
+fn test_periodic(
+) -> FuzzyResult<()> {
+    let set = crate::fuzzy! {
+        "night" => (0.0, 1.0), (1.0, 1.0), (3.0, 0.5), (5.0, 0.0), (19.0, 0.0), (21.0, 0.5), (23.0, 1.0)
+    }?.periodic(24.0)?
+        .term("late", MembershipFunction::Triangular { a: 22.0, b: 24.0, c: 26.0 })?;
+
+    assert_eq!(set.call_single("night", 23.5)?, 1.0);
+    assert_eq!(set.call_single("night", -0.5)?, 1.0);
+    assert_eq!(set.call_single("night", 26.0)?, 0.75);
+    assert_eq!(set.points("late")?, vec![(0.0, 1.0), (2.0, 0.0), (22.0, 0.0), (24.0, 1.0)]);
+    assert_eq!(set.call_single("late", 1.0)?, 0.5);
+    assert_eq!(set.call_single("late", 23.0)?, 0.5);
+    assert_eq!(set.call_single("late", 12.0)?, 0.0);
+    // Evaluated directly, memberships follow the closed curve.
+    let mut set = set.term("noon", MembershipFunction::Gaussian { mean: 12.0, sigma: 2.0 })?;
+    for term in ["night", "late", "noon"] {
+        let curve = set.points(term)?;
+        for i in -48..96 {
+            let x = i as f64/2.0;
+            let y = MembershipFunction::interpolate(&curve, x.rem_euclid(24.0));
+            assert!((set.call_single(term, x)?-y).abs() < 1e-3);
+        }
+    }
+
+    set.apply_threshold("late", 0.5)?;
+    assert_eq!(
+        set.points("late")?,
+        vec![(0.0, 0.5), (1.0, 0.5), (2.0, 0.0), (22.0, 0.0), (23.0, 0.5), (24.0, 0.5)]);
+    assert_eq!(set.call_single("late", 47.5)?, 0.5);
+    assert!(FuzzySet::new().periodic(0.0).is_err());
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&set).unwrap();
+        assert!(json.starts_with("{\"period\":24.0,\"terms\":{"));
+        assert_eq!(serde_json::from_str::<FuzzySet>(&json).unwrap(), set);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_universe(
+) -> FuzzyResult<()> {
+    let set = FuzzySet::new()
+        .universe(0.0, 100.0)?
+        .term("quiet", vec![(10.0, 1.0), (30.0, 0.0)])?
+        .term("mid", MembershipFunction::Gaussian { mean: 0.0, sigma: 10.0 })?;
+    assert_eq!(set.bounds(), Some((0.0, 100.0)));
+    assert_eq!(set.points("quiet")?, vec![(0.0, 1.0), (10.0, 1.0), (30.0, 0.0), (100.0, 0.0)]);
+    assert_eq!(set.points("mid")?.first(), Some(&(0.0, 1.0)));
+
+    assert_eq!(set.call_single("mid", -10.0)?, 1.0);
+    let extrapolate = set.clone().out_of_range(RangePolicy::Extrapolate);
+    assert_eq!(extrapolate.call_single("mid", -10.0)?, (-0.5f64).exp());
+    let zero = set.clone().out_of_range(RangePolicy::Zero);
+    assert_eq!(zero.call_single("quiet", -1.0)?, 0.0);
+    assert_eq!(zero.call_single("quiet", 0.0)?, 1.0);
+    let strict = set.clone().out_of_range(RangePolicy::Error);
+    assert_eq!(strict.call_single("quiet", 130.0), Err(FuzzyError::OutOfRange { x: 130.0, min: 0.0, max: 100.0 }));
+
+    // Shoulders are integrated up to the bound, not only over the spread of the points.
+    let high = vec![(5.0, 0.0), (8.0, 1.0)];
+    assert_eq!(crate::defuzz::cog(FuzzySet::new().term("high", high.clone())?.points("high")?)?, 7.0);
+    let bounded = FuzzySet::new().universe(0.0, 10.0)?.term("high", high)?;
+    assert_eq!(crate::defuzz::cog(bounded.points("high")?)?, 28.5/3.5);
+    assert!(FuzzySet::new().universe(1.0, 1.0).is_err());
+    assert!(FuzzySet::new().periodic(24.0)?.universe(0.0, 1.0).is_err());
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&strict).unwrap();
+        assert!(json.starts_with("{\"universe\":[0.0,100.0],\"out_of_range\":\"error\",\"terms\":{"));
+        assert_eq!(serde_json::from_str::<FuzzySet>(&json).unwrap(), strict);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_algebra(
+) -> FuzzyResult<()> {
+    let set = crate::fuzzy! {
+        "low" => (0.0, 0.0), (2.0, 1.0), (4.0, 0.0);
+        "high" => (2.0, 0.0), (4.0, 1.0), (6.0, 0.0)
+    }?;
+    let other = FuzzySet::new().term("mid", vec![(1.0, 0.0), (3.0, 1.0), (5.0, 0.0)])?;
+
+    assert_eq!(
+        set.intersection("low", &set, "high", &TNorm::Min)?,
+        MembershipFunction::Polyline(vec![(0.0, 0.0), (2.0, 0.0), (3.0, 0.5), (4.0, 0.0), (6.0, 0.0)]));
+    assert_eq!(
+        set.union("low", &set, "high", &SNorm::Max)?,
+        MembershipFunction::Polyline(vec![(0.0, 0.0), (2.0, 1.0), (3.0, 0.5), (4.0, 1.0), (6.0, 0.0)]));
+    assert_eq!(
+        set.complement("low", &Complement::Standard)?,
+        MembershipFunction::Polyline(vec![(0.0, 1.0), (2.0, 0.0), (4.0, 1.0)]));
+
+    // Terms from two sets, crossings at 1.5 and 3.5.
+    let both = set.intersection("low", &other, "mid", &TNorm::Min)?;
+    assert_eq!(both.call(1.5), 0.25);
+    assert_eq!(both.call(2.5), 0.75);
+    assert_eq!(both.call(3.5), 0.25);
+    // Other norms are sampled.
+    let product = set.intersection("low", &set, "high", &TNorm::Product)?;
+    assert!((product.call(3.0)-0.25).abs() < 1e-3);
+    let very = set.union(crate::membership::very("low"), &other, "mid", &SNorm::Max)?;
+    assert_eq!(very.call(2.0), 1.0);
+    assert_eq!(very.call(1.0), 0.25);
+
+    // Results are ordinary terms.
+    let set = set.clone().term("low or high", set.union("low", &set, "high", &SNorm::Max)?)?;
+    assert_eq!(set.call_single("low or high", 3.0)?, 0.5);
+    assert_eq!(set.union("none", &set, "low", &SNorm::Max), Err(FuzzyError::InvalidTerm("none".to_string())));
+    Ok(())
+}
+
+#[test]
+fn test_measures(
+) -> FuzzyResult<()> {
+    let set = crate::fuzzy! {
+        "mid" => (0.0, 0.0), (2.0, 1.0), (4.0, 1.0), (6.0, 0.0);
+        "bumps" => (0.0, 0.0), (1.0, 0.8), (2.0, 0.0), (3.0, 0.0), (4.0, 0.4), (5.0, 0.0);
+        "low" => (0.0, 1.0), (4.0, 0.0), (8.0, 0.0)
+    }?;
+    assert_eq!(set.support("mid")?, vec![(0.0, 6.0)]);
+    assert_eq!(set.core("mid")?, vec![(2.0, 4.0)]);
+    assert_eq!(set.alpha_cut("mid", 0.5)?, vec![(1.0, 5.0)]);
+    assert_eq!(set.crossover("mid")?, vec![(1.0, 1.0), (5.0, 5.0)]);
+    assert_eq!(set.height("mid")?, 1.0);
+    assert_eq!(set.cardinality("mid")?, 4.0);
+    assert!(set.relative_cardinality("mid").is_err());
+    assert_eq!(set.clone().universe(0.0, 8.0)?.relative_cardinality("mid")?, 0.5);
+
+    assert_eq!(set.support("bumps")?, vec![(0.0, 2.0), (3.0, 5.0)]);
+    assert_eq!(set.core("bumps")?, vec![]);
+    assert_eq!(set.alpha_cut("bumps", 0.4)?, vec![(0.5, 1.5), (4.0, 4.0)]);
+    assert_eq!(set.height("bumps")?, 0.8);
+    assert!((set.cardinality("bumps")?-1.2).abs() < 1e-12);
+
+    // Shoulders of unbounded sets reach infinity.
+    assert_eq!(set.support("low")?, vec![(f64::NEG_INFINITY, 4.0)]);
+    assert_eq!(set.core("low")?, vec![(f64::NEG_INFINITY, 0.0)]);
+    assert_eq!(set.cardinality("low")?, f64::INFINITY);
+    assert_eq!(set.crossover("low")?, vec![(2.0, 2.0)]);
+    let shelf = crate::fuzzy! { "shelf" => (0.0, 0.0), (1.0, 0.5), (2.0, 0.5), (3.0, 0.5), (4.0, 1.0), (5.0, 0.5), (6.0, 0.0) }?;
+    assert_eq!(shelf.crossover("shelf")?, vec![(1.0, 3.0), (5.0, 5.0)]);
+    assert_eq!(set.alpha_cut("low", 0.0)?, vec![(f64::NEG_INFINITY, f64::INFINITY)]);
+    assert_eq!(set.clone().universe(0.0, 8.0)?.core("low")?, vec![(0.0, 0.0)]);
+    let gauss = FuzzySet::new().term("gauss", MembershipFunction::Gaussian { mean: 0.0, sigma: 1.0 })?;
+    assert_eq!(gauss.support("gauss")?, vec![(-4.0, 4.0)]);
+    assert!(gauss.cardinality("gauss")?.is_finite());
+    assert_eq!(set.height(crate::membership::very("bumps"))?, 0.8f64.powi(2));
+    assert!(set.alpha_cut("low", 1.5).is_err());
+
+    // Periodic terms are measured over one period.
+    let night = crate::fuzzy! { "night" => (22.0, 0.0), (24.0, 1.0), (26.0, 0.0) }?.periodic(24.0)?;
+    assert_eq!(night.support("night")?, vec![(0.0, 2.0), (22.0, 24.0)]);
+    assert_eq!(night.cardinality("night")?, 2.0);
+    assert_eq!(night.relative_cardinality("night")?, 2.0/24.0);
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use super::common::{Category, FuzzyError, FuzzyResult, Term};
+
+/// Consequent of a Takagi-Sugeno-Kang rule.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SugenoTerm {
+    /// Zero-order consequent.
+    Constant(f64),
+    /// First-order consequent: sum of input values multiplied by coefficients plus constant.
+    Linear(
+        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+        HashMap<Category, f64>,
+        f64
+    )
+}
+
+/// Output of a Takagi-Sugeno-Kang model. Terms are functions of the inputs instead of fuzzy sets.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SugenoSet {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::common::serialize_sorted"))]
+    terms: HashMap<Term, SugenoTerm>
+}
+
+impl SugenoSet {
+    pub fn new(
+    ) -> Self {
+        Self {
+            terms: HashMap::new()
+        }
+    }
+
+    /// Adds zero-order term.
+    pub fn constant(
+        mut self,
+        key: impl Into<Term>,
+        value: f64
+    ) -> Self {
+        self.terms.insert(key.into(), SugenoTerm::Constant(value));
+        self
+    }
+
+    /// Adds first-order term.
+    pub fn linear<C: Into<Category>>(
+        mut self,
+        key: impl Into<Term>,
+        coefficients: Vec<(C, f64)>,
+        constant: f64
+    ) -> Self {
+        let coefficients = coefficients.into_iter()
+            .map(|(category, c)| (category.into(), c))
+            .collect();
+        self.terms.insert(key.into(), SugenoTerm::Linear(coefficients, constant));
+        self
+    }
+
+    pub fn terms(
+        &self
+    ) -> impl Iterator<Item=(&Term, &SugenoTerm)> {
+        self.terms.iter()
+    }
+
+    /// Evaluates term for given inputs.
+    pub fn call_single(
+        &self,
+        term: impl Into<Term>,
+        values: &HashMap<Category, f64>
+    ) -> FuzzyResult<f64> {
+        let key = term.into();
+        match self.terms.get(&key).ok_or(FuzzyError::InvalidTerm(key))? {
+            SugenoTerm::Constant(value) => Ok(*value),
+            SugenoTerm::Linear(coefficients, constant) => {
+                let mut y = *constant;
+                for (category, c) in coefficients.iter() {
+                    let x = values.get(category)
+                        .ok_or_else(|| FuzzyError::InvalidCategory(category.clone()))?;
+                    y += c*x;
+                }
+                Ok(y)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sugeno_set(
+) -> FuzzyResult<()> {
+    let set = SugenoSet::new()
+        .constant("zero", 0.0)
+        .linear("line", vec![("a", 2.0), ("b", -1.0)], 1.0);
+    let values = crate::values! { "a" => 3.0; "b" => 4.0 };
+    assert_eq!(set.call_single("zero", &values)?, 0.0);
+    assert_eq!(set.call_single("line", &values)?, 3.0);
+    assert_eq!(set.call_single("none", &values), Err(FuzzyError::InvalidTerm("none".to_string())));
+    let values = crate::values! { "a" => 3.0 };
+    assert_eq!(set.call_single("line", &values), Err(FuzzyError::InvalidCategory("b".to_string())));
+    Ok(())
+}